@@ -0,0 +1,263 @@
+use crate::deck::DEFAULT_DECK_SIZE;
+use rand::Rng;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The largest alphabet which can be encrypted: the keystream of a full deck
+/// produces values up to this, so a larger alphabet could not be shifted
+/// uniformly onto every symbol.
+pub const MAX_ALPHABET_SIZE: usize = DEFAULT_DECK_SIZE - 2;
+
+const LATIN: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const ALPHANUMERIC: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+const GREEK: &str = "ΑΒΓΔΕΖΗΘΙΚΛΜΝΞΟΠΡΣΤΥΦΧΨΩ";
+const CYRILLIC: &str = "АБВГДЕЁЖЗИЙКЛМНОПРСТУФХЦЧШЩЪЫЬЭЮЯ";
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum AlphabetError {
+    #[error("an alphabet needs at least 2 symbols; got {0}")]
+    TooSmall(usize),
+    #[error("an alphabet may have at most {MAX_ALPHABET_SIZE} symbols; got {0}")]
+    TooLarge(usize),
+    #[error("symbol {0:?} appears more than once")]
+    Duplicate(char),
+    #[error("symbol {0:?} is not in the alphabet")]
+    UnknownSymbol(char),
+}
+
+/// An ordered set of symbols which can be encrypted.
+///
+/// Each symbol is assigned a value, starting at 1 for the first symbol.
+/// Keystream values are reduced modulo the size of the alphabet, so an
+/// alphabet whose size does not divide [`MAX_ALPHABET_SIZE`] favors some
+/// shifts slightly over others.
+///
+/// Alphabets are case-insensitive: symbols are stored, and restored, in
+/// upper case, but input in either case is recognized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alphabet {
+    symbols: Vec<char>,
+    pad: u8,
+}
+
+/// normalize a char to its upper case, if it has a single-char upper case
+fn normalize(c: char) -> char {
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(u), None) => u,
+        _ => c,
+    }
+}
+
+/// the lower case of a char, if it is a single char
+fn lowercase(c: char) -> Option<char> {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => Some(l),
+        _ => None,
+    }
+}
+
+impl Alphabet {
+    /// Create a new alphabet from its symbols, in order.
+    ///
+    /// The padding symbol is `X` if the alphabet contains it, or the final
+    /// symbol otherwise.
+    pub fn new(symbols: impl IntoIterator<Item = char>) -> Result<Alphabet, AlphabetError> {
+        let mut out: Vec<char> = Vec::new();
+        for symbol in symbols.into_iter().map(normalize) {
+            if out.contains(&symbol) {
+                return Err(AlphabetError::Duplicate(symbol));
+            }
+            out.push(symbol);
+        }
+        if out.len() < 2 {
+            return Err(AlphabetError::TooSmall(out.len()));
+        }
+        if out.len() > MAX_ALPHABET_SIZE {
+            return Err(AlphabetError::TooLarge(out.len()));
+        }
+        let pad = out.iter().position(|&c| c == 'X').unwrap_or(out.len() - 1) as u8 + 1;
+        Ok(Alphabet { symbols: out, pad })
+    }
+
+    /// The letters `A..=Z`, as used by Schneier.
+    pub fn latin() -> Alphabet {
+        Alphabet::new(LATIN.chars()).expect("latin alphabet is valid")
+    }
+
+    /// The letters `A..=Z`, followed by the digits `0..=9`.
+    pub fn alphanumeric() -> Alphabet {
+        Alphabet::new(ALPHANUMERIC.chars()).expect("alphanumeric alphabet is valid")
+    }
+
+    /// The 24 letters of the modern Greek alphabet.
+    pub fn greek() -> Alphabet {
+        Alphabet::new(GREEK.chars()).expect("greek alphabet is valid")
+    }
+
+    /// The 33 letters of the Russian Cyrillic alphabet.
+    pub fn cyrillic() -> Alphabet {
+        Alphabet::new(CYRILLIC.chars()).expect("cyrillic alphabet is valid")
+    }
+
+    /// Choose a different padding symbol.
+    pub fn with_pad(mut self, pad: char) -> Result<Alphabet, AlphabetError> {
+        self.pad = self.value(pad).ok_or(AlphabetError::UnknownSymbol(pad))?;
+        Ok(self)
+    }
+
+    /// the number of symbols in this alphabet
+    pub fn size(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// the value of the padding symbol
+    pub fn pad_value(&self) -> u8 {
+        self.pad
+    }
+
+    /// the symbols of this alphabet, in order
+    pub fn symbols(&self) -> &[char] {
+        &self.symbols
+    }
+
+    /// Find the value of a symbol, in the range `1..=size`.
+    pub fn value(&self, c: char) -> Option<u8> {
+        let c = normalize(c);
+        self.symbols
+            .iter()
+            .position(|&s| s == c)
+            .map(|idx| idx as u8 + 1)
+    }
+
+    /// Find the symbol for a value.
+    ///
+    /// Values above the size of the alphabet wrap around.
+    pub fn symbol(&self, value: u8) -> char {
+        self.symbols[(value as usize - 1) % self.size()]
+    }
+
+    /// Choose `n` values uniformly at random.
+    pub(crate) fn random_values(&self, n: usize, rng: &mut impl Rng) -> Vec<u8> {
+        (0..n)
+            .map(|_| rng.gen_range(1..=self.size() as u8))
+            .collect()
+    }
+
+    /// Find the value of a symbol whose case can be preserved.
+    ///
+    /// This is either a symbol itself, or the single-char lower case of one.
+    /// Chars such as `ſ` or `ς` have a symbol as their upper case, but are
+    /// not its lower case, so they are not found.
+    pub(crate) fn cased_value(&self, c: char) -> Option<u8> {
+        let value = self.value(c)?;
+        let symbol = self.symbol(value);
+        (c == symbol || lowercase(symbol) == Some(c)).then_some(value)
+    }
+
+    /// Append the symbol for `value` to `out`, in the same case as `like`.
+    ///
+    /// Symbols without a single-char lower case are appended as they are.
+    pub(crate) fn push_symbol_like(&self, out: &mut String, value: u8, like: char) {
+        let symbol = self.symbol(value);
+        match lowercase(symbol) {
+            Some(lower) if like.is_lowercase() && normalize(lower) == symbol => out.push(lower),
+            _ => out.push(symbol),
+        }
+    }
+
+    /// Convert a text input into a numeric stream according to its chars.
+    ///
+    /// Chars not in this alphabet are discarded.
+    pub fn textbyte<'a>(&'a self, text: &'a str) -> impl 'a + Iterator<Item = u8> {
+        text.chars().filter_map(move |c| self.value(c))
+    }
+}
+
+impl Default for Alphabet {
+    fn default() -> Alphabet {
+        Alphabet::latin()
+    }
+}
+
+impl FromStr for Alphabet {
+    type Err = AlphabetError;
+
+    /// Parse either the name of a built-in alphabet or a literal list of symbols.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "latin" => Ok(Alphabet::latin()),
+            "alphanumeric" => Ok(Alphabet::alphanumeric()),
+            "greek" => Ok(Alphabet::greek()),
+            "cyrillic" => Ok(Alphabet::cyrillic()),
+            _ => Alphabet::new(s.chars()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::textbyte::textbyte;
+    use crate::PAD_CHAR;
+
+    #[test]
+    fn test_builtin_sizes() {
+        assert_eq!(Alphabet::latin().size(), 26);
+        assert_eq!(Alphabet::alphanumeric().size(), 36);
+        assert_eq!(Alphabet::greek().size(), 24);
+        assert_eq!(Alphabet::cyrillic().size(), 33);
+    }
+
+    #[test]
+    fn test_latin_matches_textbyte() {
+        let msg = "The quick brown fox jumps over the lazy dog.";
+        let latin = Alphabet::latin();
+        assert!(latin.textbyte(msg).eq(textbyte(msg)));
+        assert_eq!(latin.pad_value(), PAD_CHAR);
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let greek = Alphabet::greek();
+        assert_eq!(greek.value('α'), Some(1));
+        assert_eq!(greek.value('ς'), greek.value('Σ'));
+        assert_eq!(greek.symbol(1), 'Α');
+    }
+
+    #[test]
+    fn test_pad() {
+        assert_eq!(Alphabet::greek().pad_value(), 24);
+        assert_eq!(Alphabet::latin().with_pad('q').unwrap().pad_value(), 17);
+        assert_eq!(
+            Alphabet::latin().with_pad('?'),
+            Err(AlphabetError::UnknownSymbol('?'))
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(
+            Alphabet::new("aba".chars()),
+            Err(AlphabetError::Duplicate('A'))
+        );
+        assert_eq!(Alphabet::new("a".chars()), Err(AlphabetError::TooSmall(1)));
+        let large = (0..=MAX_ALPHABET_SIZE as u32).map(|n| char::from_u32(0x4E00 + n).unwrap());
+        assert_eq!(
+            Alphabet::new(large),
+            Err(AlphabetError::TooLarge(MAX_ALPHABET_SIZE + 1))
+        );
+        let largest = (0..MAX_ALPHABET_SIZE as u32).map(|n| char::from_u32(0x4E00 + n).unwrap());
+        assert!(Alphabet::new(largest).is_ok());
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("Greek".parse::<Alphabet>().unwrap(), Alphabet::greek());
+        assert_eq!(
+            "abc".parse::<Alphabet>().unwrap().symbols(),
+            &['A', 'B', 'C']
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::deck::DEFAULT_DECK_SIZE;
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt;
 use std::str::FromStr;
@@ -30,6 +31,12 @@ pub enum CardConversionError {
     CouldNotParseRank(#[from] std::num::ParseIntError),
     #[error("unknown joker: need A or B; got {0}")]
     UnknownJoker(String),
+    #[error("{0:?} is not a card of a standard deck")]
+    UnknownGlyph(char),
+    #[error("a joker name must look like NAME=A or NAME=B; got {0:?}")]
+    MalformedJokerName(String),
+    #[error("unknown card name {0:?}")]
+    UnknownName(String),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -80,16 +87,14 @@ impl<const DECK_SIZE: usize> fmt::Display for Suit<DECK_SIZE> {
 impl<const DECK_SIZE: usize> Suit<DECK_SIZE> {
     pub fn to_ascii_string(&self) -> String {
         use Suit::*;
-        format!(
-            "{}",
-            match self {
-                Club => "C",
-                Diamond => "D",
-                Heart => "H",
-                Spade => "S",
-                Joker => "J",
-            }
-        )
+        match self {
+            Club => "C",
+            Diamond => "D",
+            Heart => "H",
+            Spade => "S",
+            Joker => "J",
+        }
+        .to_string()
     }
 }
 
@@ -161,9 +166,9 @@ impl fmt::Display for Card<DEFAULT_DECK_SIZE> {
 impl Card<DEFAULT_DECK_SIZE> {
     fn rank_string(&self) -> String {
         use Rank::*;
-        match self {
-            &JOKER_A => "A".to_string(),
-            &JOKER_B => "B".to_string(),
+        match *self {
+            JOKER_A => "A".to_string(),
+            JOKER_B => "B".to_string(),
             _ => {
                 let mut s = String::with_capacity(2);
                 match self.rank {
@@ -180,20 +185,300 @@ impl Card<DEFAULT_DECK_SIZE> {
     pub fn to_ascii_string(&self) -> String {
         format!("{}{}", self.rank_string(), self.suit.to_ascii_string())
     }
+
+    /// Render this card in the given format.
+    pub fn format(&self, format: CardFormat) -> String {
+        match format {
+            CardFormat::Symbols => self.to_string(),
+            CardFormat::Ascii => self.to_ascii_string(),
+            CardFormat::Glyph => self.glyph().to_string(),
+            CardFormat::Name => self.name(),
+            CardFormat::Number => u8::from(self).to_string(),
+            CardFormat::Ansi if self.is_red() => format!("{}{}{}", ANSI_RED, self, ANSI_RESET),
+            CardFormat::Ansi => self.to_string(),
+        }
+    }
+
+    /// whether this card is printed in red: the hearts, the diamonds, and joker A
+    fn is_red(&self) -> bool {
+        matches!(self.suit, Suit::Heart | Suit::Diamond) || *self == JOKER_A
+    }
+
+    /// the character of the Unicode playing cards block showing this card
+    ///
+    /// Joker A is the red joker, and joker B the black joker.
+    pub fn glyph(&self) -> char {
+        use Rank::*;
+        let offset = match *self {
+            JOKER_A => 0x1f,
+            JOKER_B => 0x2f,
+            _ => {
+                let row = match self.suit {
+                    Suit::Spade => 0,
+                    Suit::Heart => 1,
+                    Suit::Diamond => 2,
+                    Suit::Club => 3,
+                    Suit::Joker => unreachable!("jokers are handled above"),
+                };
+                let column = match self.rank {
+                    Number(n) => n as u32,
+                    Jack => 0xb,
+                    Queen => 0xd,
+                    King => 0xe,
+                };
+                row * 16 + column
+            }
+        };
+        std::char::from_u32(GLYPH_BASE + offset).expect("playing card glyphs are valid chars")
+    }
+
+    /// the English name of this card, such as `Ace of Clubs` or `Joker A`
+    pub fn name(&self) -> String {
+        match *self {
+            JOKER_A => "Joker A".to_string(),
+            JOKER_B => "Joker B".to_string(),
+            _ => {
+                let rank = RANK_NAMES[self.rank.value() as usize - 1];
+                let suit = match self.suit {
+                    Suit::Club => "Clubs",
+                    Suit::Diamond => "Diamonds",
+                    Suit::Heart => "Hearts",
+                    Suit::Spade => "Spades",
+                    Suit::Joker => unreachable!("jokers are handled above"),
+                };
+                format!("{} of {}", rank, suit)
+            }
+        }
+    }
 }
 
-impl FromStr for Card<DEFAULT_DECK_SIZE> {
+const RANK_NAMES: [&str; 13] = [
+    "Ace", "Two", "Three", "Four", "Five", "Six", "Seven", "Eight", "Nine", "Ten", "Jack", "Queen",
+    "King",
+];
+
+const ANSI_RED: &str = "\x1b[31m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+/// A way of writing a card.
+///
+/// Every format can be parsed back by [`Card::parse_with`] and
+/// [`MaybeDeck`](crate::deck::MaybeDeck).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CardFormat {
+    /// the rank and a suit symbol, such as `10♡`
+    #[default]
+    Symbols,
+    /// the rank and a suit letter, such as `10H`
+    Ascii,
+    /// a character of the Unicode playing cards block, such as `🂺`
+    Glyph,
+    /// the English name, such as `Ten of Hearts`
+    Name,
+    /// the value of the card from 1 to 54, as in Schneier's test vectors
+    Number,
+    /// the rank and a suit symbol, with red cards colored by ANSI escape codes
+    Ansi,
+}
+
+impl CardFormat {
+    /// what separates cards when writing a deck in this format
+    ///
+    /// Names are separated by commas only for legibility: both parsers find
+    /// where each name ends without them.
+    pub fn separator(self) -> &'static str {
+        match self {
+            CardFormat::Name => ", ",
+            _ => " ",
+        }
+    }
+}
+
+/// parse an English card name, such as `Ace of Clubs` or `Joker A`
+fn from_name(s: &str) -> Option<Result<Card, CardConversionError>> {
+    let words: Vec<_> = s.split_whitespace().collect();
+    let unknown = || CardConversionError::UnknownName(s.into());
+    match words[..] {
+        [joker, which] if joker.eq_ignore_ascii_case("joker") => Some(
+            which
+                .parse::<Joker>()
+                .map(Joker::card)
+                .map_err(|_| unknown()),
+        ),
+        [rank, of, suit] if of.eq_ignore_ascii_case("of") => {
+            let rank = RANK_NAMES
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(rank))
+                .ok_or_else(unknown);
+            let suit = match suit.to_lowercase().trim_end_matches('s') {
+                "club" => Ok(Suit::Club),
+                "diamond" => Ok(Suit::Diamond),
+                "heart" => Ok(Suit::Heart),
+                "spade" => Ok(Suit::Spade),
+                _ => Err(unknown()),
+            };
+            Some(rank.and_then(|rank| {
+                Ok(Card::new(
+                    suit?,
+                    Rank::try_from(rank as u8 + 1).expect("ranks of RANK_NAMES are valid"),
+                ))
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// the first code point of the Unicode playing cards block, the back of a card
+const GLYPH_BASE: u32 = 0x1F0A0;
+
+/// Which of the two jokers a card is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Joker {
+    A,
+    B,
+}
+
+impl Joker {
+    pub fn card(self) -> Card {
+        match self {
+            Joker::A => JOKER_A,
+            Joker::B => JOKER_B,
+        }
+    }
+}
+
+impl FromStr for Joker {
     type Err = CardConversionError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let suit_s = &s
-            .chars()
-            .last()
-            .ok_or(CardConversionError::WrongLength(0))?
-            .to_string();
-        let suit: Suit = str::parse(&suit_s)?;
-
-        let rank_s = &s[..s.len() - suit_s.len()];
+        match s.trim() {
+            "A" | "a" => Ok(Joker::A),
+            "B" | "b" => Ok(Joker::B),
+            other => Err(CardConversionError::UnknownJoker(other.into())),
+        }
+    }
+}
+
+/// The names by which the jokers of a physical deck are written.
+///
+/// `AJ` and `BJ` always name jokers A and B, unless they are given another
+/// meaning here. By default, the red joker (`RJ`, `🂿`) is joker A and the
+/// black joker (`BJ`, `🃏`) is joker B. Names are not case sensitive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JokerNames(HashMap<String, Joker>);
+
+impl JokerNames {
+    /// joker names with no entries, so that only `AJ` and `BJ` are recognized
+    pub fn empty() -> JokerNames {
+        JokerNames(HashMap::new())
+    }
+
+    /// Name a joker, replacing any previous meaning of the name.
+    pub fn insert(&mut self, name: &str, joker: Joker) {
+        self.0.insert(name.to_uppercase(), joker);
+    }
+
+    /// the joker with this name, if any
+    pub fn get(&self, name: &str) -> Option<Joker> {
+        self.0.get(&name.to_uppercase()).copied()
+    }
+
+    /// every name, in no particular order
+    pub fn names(&self) -> impl '_ + Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+}
+
+impl Default for JokerNames {
+    fn default() -> JokerNames {
+        let mut names = JokerNames::empty();
+        names.insert("RJ", Joker::A);
+        names.insert("\u{1F0BF}", Joker::A);
+        names.insert("BJ", Joker::B);
+        names.insert("\u{1F0CF}", Joker::B);
+        names
+    }
+}
+
+impl FromStr for JokerNames {
+    type Err = CardConversionError;
+
+    /// Parse a comma-separated list of `NAME=A` or `NAME=B` pairs.
+    ///
+    /// They are added to the default names, replacing any default meaning.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut names = JokerNames::default();
+        for pair in s.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let (name, joker) = pair
+                .split_once('=')
+                .ok_or_else(|| CardConversionError::MalformedJokerName(pair.trim().into()))?;
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(CardConversionError::MalformedJokerName(pair.trim().into()));
+            }
+            names.insert(name, joker.parse()?);
+        }
+        Ok(names)
+    }
+}
+
+/// decode a single character of the Unicode playing cards block
+fn from_glyph(glyph: char) -> Option<Result<Card, CardConversionError>> {
+    let offset = (glyph as u32).checked_sub(GLYPH_BASE)?;
+    if offset >= 0x40 {
+        return None;
+    }
+    let suit = match offset >> 4 {
+        0 => Suit::Spade,
+        1 => Suit::Heart,
+        2 => Suit::Diamond,
+        _ => Suit::Club,
+    };
+    use Rank::*;
+    let rank = match offset & 0xf {
+        n @ 1..=10 => Number(n as u8),
+        0xb => Jack,
+        0xd => Queen,
+        0xe => King,
+        // the back of a card, the knights, and the jokers not named in `JokerNames`
+        _ => return Some(Err(CardConversionError::UnknownGlyph(glyph))),
+    };
+    Some(Ok(Card::new(suit, rank)))
+}
+
+impl Card<DEFAULT_DECK_SIZE> {
+    /// Parse a card, recognizing the jokers by the given names.
+    ///
+    /// Besides those names, a card can be written as its rank followed by its
+    /// suit, such as `10h`, `Th`, or `10♥`; as a single character of the
+    /// Unicode playing cards block, such as `🂺`; as its English name, such as
+    /// `Ten of Hearts`; or as its value from 1 to 54.
+    pub fn parse_with(s: &str, jokers: &JokerNames) -> Result<Self, CardConversionError> {
+        if let Some(joker) = jokers.get(s) {
+            return Ok(joker.card());
+        }
+        if let Some(card) = from_name(s) {
+            return card;
+        }
+        if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+            return Card::try_from(
+                s.parse::<u8>()
+                    .map_err(|_| CardConversionError::ValueOutOfRange)?,
+            );
+        }
+
+        let mut chars = s.chars();
+        let suit_c = chars
+            .next_back()
+            .ok_or(CardConversionError::WrongLength(0))?;
+        if chars.as_str().is_empty() {
+            if let Some(card) = from_glyph(suit_c) {
+                return card;
+            }
+        }
+        let suit: Suit = str::parse(&suit_c.to_string())?;
+
+        let rank_s = chars.as_str();
         match (suit, rank_s) {
             (Suit::Joker, "A") | (Suit::Joker, "a") => Ok(JOKER_A),
             (Suit::Joker, "B") | (Suit::Joker, "b") => Ok(JOKER_B),
@@ -204,8 +489,12 @@ impl FromStr for Card<DEFAULT_DECK_SIZE> {
                     "11" | "J" | "j" => Jack,
                     "12" | "Q" | "q" => Queen,
                     "13" | "K" | "k" => King,
+                    "T" | "t" => Number(10),
                     "A" | "a" => Number(1),
-                    _ => Number(str::parse(rank_s)?),
+                    _ => match str::parse(rank_s)? {
+                        n @ 1..=10 => Number(n),
+                        _ => return Err(CardConversionError::ValueOutOfRange),
+                    },
                 };
                 Ok(Card::new(suit, rank))
             }
@@ -213,6 +502,14 @@ impl FromStr for Card<DEFAULT_DECK_SIZE> {
     }
 }
 
+impl FromStr for Card<DEFAULT_DECK_SIZE> {
+    type Err = CardConversionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Card::parse_with(s, &JokerNames::default())
+    }
+}
+
 impl<const DECK_SIZE: usize> Default for Card<DECK_SIZE> {
     fn default() -> Self {
         Card {
@@ -301,4 +598,65 @@ mod tests {
             assert_eq!(card, parsed);
         }
     }
+
+    #[test]
+    fn test_notations() {
+        let ten_of_hearts = Card::new(Suit::Heart, Rank::Number(10));
+        for s in &["10h", "Th", "t♥", "\u{1F0BA}"] {
+            assert_eq!(Card::from_str(s).unwrap(), ten_of_hearts, "{}", s);
+        }
+        assert_eq!(
+            Card::from_str("\u{1F0D1}").unwrap(),
+            Card::new(Suit::Club, Rank::Number(1))
+        );
+        assert_eq!(
+            Card::from_str("\u{1F0AE}").unwrap(),
+            Card::new(Suit::Spade, Rank::King)
+        );
+        // the knight of spades
+        assert!(Card::from_str("\u{1F0AC}").is_err());
+        assert!(Card::from_str("14h").is_err());
+    }
+
+    #[test]
+    fn test_joker_names() {
+        for (s, joker) in &[
+            ("AJ", JOKER_A),
+            ("rj", JOKER_A),
+            ("\u{1F0BF}", JOKER_A),
+            ("BJ", JOKER_B),
+            ("\u{1F0CF}", JOKER_B),
+        ] {
+            assert_eq!(Card::from_str(s).unwrap(), *joker, "{}", s);
+        }
+
+        let swapped: JokerNames = "BJ=A, RJ=B, \u{1F0CF}=a".parse().unwrap();
+        assert_eq!(Card::parse_with("bj", &swapped).unwrap(), JOKER_A);
+        assert_eq!(Card::parse_with("RJ", &swapped).unwrap(), JOKER_B);
+        assert_eq!(Card::parse_with("\u{1F0CF}", &swapped).unwrap(), JOKER_A);
+        assert_eq!(Card::parse_with("AJ", &swapped).unwrap(), JOKER_A);
+
+        assert!("BJ".parse::<JokerNames>().is_err());
+        assert!("BJ=C".parse::<JokerNames>().is_err());
+        assert!(Card::parse_with("RJ", &JokerNames::empty()).is_err());
+    }
+
+    #[test]
+    fn test_formats() {
+        let card = Card::new(Suit::Heart, Rank::Number(10));
+        assert_eq!(card.format(CardFormat::Symbols), "10♡");
+        assert_eq!(card.format(CardFormat::Ascii), "10H");
+        assert_eq!(card.format(CardFormat::Glyph), "\u{1F0BA}");
+        assert_eq!(card.format(CardFormat::Name), "Ten of Hearts");
+        assert_eq!(card.format(CardFormat::Number), "36");
+        assert_eq!(card.format(CardFormat::Ansi), "\x1b[31m10♡\x1b[0m");
+        assert_eq!(JOKER_B.format(CardFormat::Name), "Joker B");
+        assert_eq!(JOKER_A.format(CardFormat::Glyph), "\u{1F0BF}");
+        assert_eq!(
+            Card::from_str("queen of spade").unwrap(),
+            Card::new(Suit::Spade, Rank::Queen)
+        );
+        assert!(Card::from_str("Knight of Spades").is_err());
+        assert!(Card::from_str("55").is_err());
+    }
 }
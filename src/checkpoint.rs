@@ -0,0 +1,246 @@
+//! Saved positions in a keystream, from which it can be resumed.
+//!
+//! Reaching the 500th value of a keystream means generating the 499 before
+//! it. A [`Checkpoint`] records the deck at some position, so that the
+//! keystream can be resumed from there instead; [`Checkpoints`] keeps one
+//! every so often, so that any position is a short way past the nearest.
+//!
+//! Checkpoints are stored as plain text, one per line:
+//!
+//! ```text
+//! <position> <skipped> <rank of the deck, with its encoding prefix>
+//! ```
+//!
+//! Blank lines and lines beginning with `#` are ignored. A checkpoint holds
+//! the deck itself, so it must be kept as secret as the key.
+
+use crate::deck::{DeckError, DEFAULT_DECK_SIZE};
+use crate::rank::{Rank, RankEncoding};
+use crate::{Deck, Keystream};
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum CheckpointError {
+    #[error("a checkpoint needs a position, a skip count, and a deck rank; got {0:?}")]
+    Malformed(String),
+    #[error("the checkpoint's deck is invalid")]
+    Deck(#[from] DeckError),
+    #[error("line {line} of the checkpoints is malformed")]
+    Line {
+        line: usize,
+        source: Box<CheckpointError>,
+    },
+}
+
+/// The state of a keystream after producing some number of values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint<const DECK_SIZE: usize = DEFAULT_DECK_SIZE> {
+    /// how many values the keystream had produced
+    pub position: usize,
+    /// how many times the output card had been a joker
+    pub skipped: usize,
+    /// the deck, ready to produce the value at `position`
+    pub deck: Deck<DECK_SIZE>,
+}
+
+impl<const DECK_SIZE: usize> Checkpoint<DECK_SIZE> {
+    /// Resume the keystream from this checkpoint.
+    pub fn keystream(&self) -> Keystream<DECK_SIZE> {
+        self.clone().into()
+    }
+}
+
+impl<const DECK_SIZE: usize> fmt::Display for Checkpoint<DECK_SIZE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.position,
+            self.skipped,
+            self.deck.to_rank_string(RankEncoding::Base32)
+        )
+    }
+}
+
+impl<const DECK_SIZE: usize> FromStr for Checkpoint<DECK_SIZE> {
+    type Err = CheckpointError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let malformed = || CheckpointError::Malformed(s.to_string());
+        let mut fields = s.trim().splitn(3, char::is_whitespace);
+        let mut next = || fields.next().ok_or_else(malformed);
+        let position = next()?.parse().map_err(|_| malformed())?;
+        let skipped = next()?.parse().map_err(|_| malformed())?;
+        let rank: Rank = next()?.parse().map_err(DeckError::from)?;
+        Ok(Checkpoint {
+            position,
+            skipped,
+            deck: Deck::from_rank(&rank)?,
+        })
+    }
+}
+
+/// Checkpoints along a single keystream, in order of position.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Checkpoints<const DECK_SIZE: usize = DEFAULT_DECK_SIZE> {
+    checkpoints: Vec<Checkpoint<DECK_SIZE>>,
+}
+
+impl<const DECK_SIZE: usize> Checkpoints<DECK_SIZE> {
+    /// Create a new, empty set of checkpoints.
+    pub fn new() -> Checkpoints<DECK_SIZE> {
+        Checkpoints {
+            checkpoints: Vec::new(),
+        }
+    }
+
+    /// Record a checkpoint every `interval` values of the keystream from
+    /// `deck`, through the first `length` values.
+    ///
+    /// The first checkpoint is the deck itself, at position 0.
+    pub fn every(
+        deck: Deck<DECK_SIZE>,
+        interval: NonZeroUsize,
+        length: usize,
+    ) -> Checkpoints<DECK_SIZE> {
+        let interval = interval.get();
+        let mut keystream = Keystream::from(deck);
+        let mut checkpoints = Checkpoints::new();
+        checkpoints.insert(keystream.checkpoint());
+        while keystream.position() + interval <= length {
+            keystream
+                .skip_to(keystream.position() + interval)
+                .expect("seeking forward always succeeds");
+            checkpoints.insert(keystream.checkpoint());
+        }
+        checkpoints
+    }
+
+    /// every checkpoint, in order of position
+    pub fn checkpoints(&self) -> &[Checkpoint<DECK_SIZE>] {
+        &self.checkpoints
+    }
+
+    /// Add a checkpoint, replacing any at the same position.
+    pub fn insert(&mut self, checkpoint: Checkpoint<DECK_SIZE>) {
+        match self
+            .checkpoints
+            .binary_search_by_key(&checkpoint.position, |c| c.position)
+        {
+            Ok(idx) => self.checkpoints[idx] = checkpoint,
+            Err(idx) => self.checkpoints.insert(idx, checkpoint),
+        }
+    }
+
+    /// the last checkpoint at or before `position`, if any
+    pub fn nearest(&self, position: usize) -> Option<&Checkpoint<DECK_SIZE>> {
+        let idx = self
+            .checkpoints
+            .partition_point(|checkpoint| checkpoint.position <= position);
+        idx.checked_sub(1).map(|idx| &self.checkpoints[idx])
+    }
+
+    /// Resume the keystream from the nearest checkpoint, and skip forward to
+    /// `position`.
+    pub fn keystream_at(&self, position: usize) -> Option<Keystream<DECK_SIZE>> {
+        let mut keystream = self.nearest(position)?.keystream();
+        keystream
+            .skip_to(position)
+            .expect("the nearest checkpoint is not after the position");
+        Some(keystream)
+    }
+}
+
+impl<const DECK_SIZE: usize> fmt::Display for Checkpoints<DECK_SIZE> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for checkpoint in &self.checkpoints {
+            writeln!(f, "{}", checkpoint)?;
+        }
+        Ok(())
+    }
+}
+
+impl<const DECK_SIZE: usize> FromStr for Checkpoints<DECK_SIZE> {
+    type Err = CheckpointError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut checkpoints = Checkpoints::new();
+        for (idx, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let checkpoint = line.parse().map_err(|err| CheckpointError::Line {
+                line: idx + 1,
+                source: Box::new(err),
+            })?;
+            checkpoints.insert(checkpoint);
+        }
+        Ok(checkpoints)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume() {
+        let deck: Deck = Deck::from_passphrase("checkpoint");
+        let expected: Vec<_> = Keystream::from(deck.clone()).take(100).collect();
+
+        let mut keystream = Keystream::from(deck.clone());
+        keystream.skip_to(60).unwrap();
+        let checkpoint = keystream.checkpoint();
+        assert_eq!(checkpoint.position, 60);
+        assert!(checkpoint
+            .keystream()
+            .take(40)
+            .eq(expected[60..].iter().copied()));
+        // rounds which output a joker produced no value
+        let mut counted = Keystream::from(deck);
+        counted.by_ref().take(60).for_each(drop);
+        assert_eq!(checkpoint.skipped, counted.skipped());
+        assert_eq!(checkpoint.keystream().skipped(), counted.skipped());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let checkpoints = Checkpoints::every(
+            Deck::from_passphrase("checkpoint"),
+            NonZeroUsize::new(25).unwrap(),
+            100,
+        );
+        let positions: Vec<_> = checkpoints
+            .checkpoints()
+            .iter()
+            .map(|checkpoint| checkpoint.position)
+            .collect();
+        assert_eq!(positions, [0, 25, 50, 75, 100]);
+        let text = format!("# every 25\n\n{}", checkpoints);
+        assert_eq!(text.parse::<Checkpoints>().unwrap(), checkpoints);
+        assert!(matches!(
+            "0 0 b32:AAAA\n1 zero b32:AAAA".parse::<Checkpoints>(),
+            Err(CheckpointError::Line { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_keystream_at() {
+        let deck: Deck = Deck::from_passphrase("checkpoint");
+        let expected: Vec<_> = Keystream::from(deck.clone()).take(100).collect();
+        let checkpoints = Checkpoints::every(deck, NonZeroUsize::new(30).unwrap(), 100);
+        assert_eq!(checkpoints.nearest(59).unwrap().position, 30);
+        assert_eq!(checkpoints.nearest(60).unwrap().position, 60);
+        let mut keystream = checkpoints.keystream_at(71).unwrap();
+        assert_eq!(keystream.position(), 71);
+        assert!(keystream
+            .by_ref()
+            .take(29)
+            .eq(expected[71..].iter().copied()));
+        assert_eq!(Checkpoints::<DEFAULT_DECK_SIZE>::new().nearest(0), None);
+    }
+}
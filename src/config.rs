@@ -0,0 +1,449 @@
+use crate::alphabet::{Alphabet, AlphabetError};
+use crate::{CipherError, GROUP_SIZE};
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    #[error(transparent)]
+    Alphabet(#[from] AlphabetError),
+    #[error("separator {0:?} must not be a symbol of the alphabet")]
+    SeparatorInAlphabet(char),
+    #[error("length-marked padding needs at most {alphabet} symbols per group; got {group}")]
+    GroupTooLarge { group: usize, alphabet: usize },
+    #[error("unknown padding scheme: need fill or length; got {0}")]
+    UnknownPadding(String),
+}
+
+/// How the final group of a message is padded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Padding {
+    /// Fill the final group with the pad symbol.
+    ///
+    /// This is Schneier's scheme. The padding cannot be told apart from the
+    /// message, so it is not removed on decryption.
+    #[default]
+    Fill,
+    /// Fill the final group with `n` copies of the `n`th symbol of the
+    /// alphabet, always adding at least one symbol.
+    ///
+    /// Decryption strips exactly this padding, and rejects messages whose
+    /// padding is malformed.
+    LengthMarked,
+}
+
+impl FromStr for Padding {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "fill" => Ok(Padding::Fill),
+            "length" | "length-marked" => Ok(Padding::LengthMarked),
+            _ => Err(ConfigError::UnknownPadding(s.into())),
+        }
+    }
+}
+
+/// How text is read and written by the cipher.
+///
+/// The default configuration is Schneier's: latin letters, in groups of
+/// five separated by spaces, padded with `X`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CipherConfig {
+    pub(crate) alphabet: Alphabet,
+    pub(crate) group_size: usize,
+    pub(crate) separator: char,
+    pub(crate) groups_per_line: Option<usize>,
+    pub(crate) preserve: bool,
+    pub(crate) padding: Padding,
+    pub(crate) indicator_length: usize,
+    pub(crate) tag_length: usize,
+}
+
+impl Default for CipherConfig {
+    fn default() -> CipherConfig {
+        CipherConfig {
+            alphabet: Alphabet::default(),
+            group_size: GROUP_SIZE,
+            separator: ' ',
+            groups_per_line: None,
+            preserve: false,
+            padding: Padding::default(),
+            indicator_length: 0,
+            tag_length: 0,
+        }
+    }
+}
+
+impl CipherConfig {
+    /// begin building a configuration from the defaults
+    pub fn builder() -> CipherConfigBuilder {
+        CipherConfigBuilder::default()
+    }
+
+    /// the alphabet whose symbols are transformed
+    pub fn alphabet(&self) -> &Alphabet {
+        &self.alphabet
+    }
+
+    /// the number of symbols per group, or 0 if the output is ungrouped
+    pub fn group_size(&self) -> usize {
+        self.group_size
+    }
+
+    /// whether everything but the symbols of the alphabet is preserved
+    pub fn preserves_format(&self) -> bool {
+        self.preserve
+    }
+
+    /// the value of a char of text, if it is a symbol
+    ///
+    /// When preserving the format, a char whose case would not survive the
+    /// round trip is not a symbol, and is copied through unchanged.
+    pub(crate) fn symbol_value(&self, c: char) -> Option<u8> {
+        if self.preserve {
+            self.alphabet.cased_value(c)
+        } else {
+            self.alphabet.value(c)
+        }
+    }
+
+    /// the length to which output must be padded
+    pub(crate) fn pad_length(&self) -> usize {
+        if self.preserve {
+            1
+        } else {
+            self.group_size.max(1)
+        }
+    }
+
+    /// the padding scheme for the final group
+    pub fn padding(&self) -> Padding {
+        self.padding
+    }
+
+    /// the number of random indicator symbols which begin each message
+    pub fn indicator_length(&self) -> usize {
+        self.indicator_length
+    }
+
+    /// split the indicator from the beginning of a ciphertext
+    ///
+    /// Returns the values of the indicator and the remaining text. In
+    /// format-preserving mode, the separator following the indicator is
+    /// also removed.
+    pub(crate) fn split_indicator<'a>(
+        &self,
+        text: &'a str,
+    ) -> Result<(Vec<u8>, &'a str), CipherError> {
+        let mut values = Vec::with_capacity(self.indicator_length);
+        let mut chars = text.char_indices();
+        while values.len() < self.indicator_length {
+            let (_, c) = chars.next().ok_or(CipherError::MissingIndicator)?;
+            values.extend(self.symbol_value(c));
+        }
+        let mut rest = chars.as_str();
+        if self.preserve {
+            rest = rest.strip_prefix(self.separator).unwrap_or(rest);
+        }
+        Ok((values, rest))
+    }
+
+    /// the number of authentication symbols which end each message
+    pub fn tag_length(&self) -> usize {
+        self.tag_length
+    }
+
+    /// split the authentication tag from the end of a ciphertext
+    ///
+    /// Returns the remaining text and the values of the tag. In
+    /// format-preserving mode, the separator preceding the tag is also
+    /// removed.
+    pub(crate) fn split_tag<'a>(&self, text: &'a str) -> Result<(&'a str, Vec<u8>), CipherError> {
+        let mut values = Vec::with_capacity(self.tag_length);
+        let mut chars = text.char_indices().rev();
+        let mut start = text.len();
+        while values.len() < self.tag_length {
+            let (idx, c) = chars.next().ok_or(CipherError::MissingTag)?;
+            values.extend(self.symbol_value(c));
+            start = idx;
+        }
+        values.reverse();
+        let mut rest = &text[..start];
+        if self.preserve {
+            rest = rest.strip_suffix(self.separator).unwrap_or(rest);
+        }
+        Ok((rest, values))
+    }
+
+    /// whether `c` is whitespace which can end a text after its tag
+    pub(crate) fn is_trailing(&self, c: char) -> bool {
+        c.is_whitespace() && self.symbol_value(c).is_none()
+    }
+
+    /// split the whitespace from the end of an authenticated text whose
+    /// format is preserved
+    ///
+    /// The tag goes before this whitespace, so that a final newline stays
+    /// final. Otherwise, there is none to split.
+    pub(crate) fn split_trailing<'a>(&self, text: &'a str) -> (&'a str, &'a str) {
+        if !self.preserve || self.tag_length == 0 {
+            return (text, "");
+        }
+        let body = text.trim_end_matches(|c| self.is_trailing(c));
+        text.split_at(body.len())
+    }
+
+    /// the padding values to append to a message of `len` values
+    pub(crate) fn padding_for(&self, len: usize) -> Vec<u8> {
+        let pad_length = self.pad_length();
+        match self.padding {
+            Padding::Fill => {
+                let n = (pad_length - len % pad_length) % pad_length;
+                vec![self.alphabet.pad_value(); n]
+            }
+            Padding::LengthMarked => {
+                let n = pad_length - len % pad_length;
+                vec![n as u8; n]
+            }
+        }
+    }
+
+    /// remove the padding from a decrypted message
+    ///
+    /// Fill padding can't be identified, so it is left in place.
+    pub(crate) fn unpad(&self, values: &mut Vec<u8>) -> Result<(), CipherError> {
+        if self.padding == Padding::Fill || self.preserve {
+            return Ok(());
+        }
+        let pad_length = self.pad_length();
+        if values.is_empty() || !values.len().is_multiple_of(pad_length) {
+            return Err(CipherError::MalformedPadding);
+        }
+        let n = values[values.len() - 1];
+        if n == 0 || n as usize > pad_length {
+            return Err(CipherError::MalformedPadding);
+        }
+        let start = values.len() - n as usize;
+        if values[start..].iter().any(|&v| v != n) {
+            return Err(CipherError::MalformedPadding);
+        }
+        values.truncate(start);
+        Ok(())
+    }
+
+    /// the separator, if any, to insert before the symbol at `idx`
+    pub(crate) fn separator_before(&self, idx: usize) -> Option<char> {
+        if self.preserve || self.group_size == 0 || idx == 0 || !idx.is_multiple_of(self.group_size)
+        {
+            return None;
+        }
+        match self.groups_per_line {
+            Some(per_line) if (idx / self.group_size).is_multiple_of(per_line) => Some('\n'),
+            _ => Some(self.separator),
+        }
+    }
+
+    /// collect a stream of symbols into groups
+    pub(crate) fn separate(&self, symbols: impl Iterator<Item = char>) -> String {
+        let mut out = String::new();
+        for (idx, symbol) in symbols.enumerate() {
+            if let Some(separator) = self.separator_before(idx) {
+                out.push(separator);
+            }
+            out.push(symbol);
+        }
+        out
+    }
+}
+
+/// Builds a [`CipherConfig`], validating it on completion.
+#[derive(Debug, Clone, Default)]
+pub struct CipherConfigBuilder {
+    config: CipherConfig,
+    pad: Option<char>,
+}
+
+impl CipherConfigBuilder {
+    /// transform the symbols of `alphabet`
+    pub fn alphabet(mut self, alphabet: Alphabet) -> Self {
+        self.config.alphabet = alphabet;
+        self
+    }
+
+    /// emit groups of `group_size` symbols; 0 disables grouping and padding
+    pub fn group_size(mut self, group_size: usize) -> Self {
+        self.config.group_size = group_size;
+        self
+    }
+
+    /// pad the final group with `pad`, which must be a symbol of the alphabet
+    pub fn pad(mut self, pad: char) -> Self {
+        self.pad = Some(pad);
+        self
+    }
+
+    /// separate groups with `separator`
+    pub fn separator(mut self, separator: char) -> Self {
+        self.config.separator = separator;
+        self
+    }
+
+    /// begin a new line after every `groups_per_line` groups; 0 never does
+    pub fn groups_per_line(mut self, groups_per_line: usize) -> Self {
+        self.config.groups_per_line = (groups_per_line != 0).then_some(groups_per_line);
+        self
+    }
+
+    /// only transform symbols of the alphabet, keeping their case and every other char
+    ///
+    /// This disables grouping and padding.
+    pub fn preserve_format(mut self, preserve: bool) -> Self {
+        self.config.preserve = preserve;
+        self
+    }
+
+    /// pad the final group according to `padding`
+    pub fn padding(mut self, padding: Padding) -> Self {
+        self.config.padding = padding;
+        self
+    }
+
+    /// begin each message with `indicator_length` random symbols
+    ///
+    /// The deck is keyed with the indicator, exactly as with the letters of
+    /// a passphrase, before the message is encrypted. The indicator itself
+    /// is sent in the clear as the first group, so that the recipient can
+    /// key their deck in the same way. This ensures that messages sent from
+    /// the same initial deck never share keystream.
+    pub fn indicator_length(mut self, indicator_length: usize) -> Self {
+        self.config.indicator_length = indicator_length;
+        self
+    }
+
+    /// end each message with `tag_length` authentication symbols
+    ///
+    /// After the message, the deck absorbs each letter of the plaintext as it
+    /// would a letter of a passphrase, then the tag is drawn from the
+    /// keystream. Decryption recomputes the tag, and fails if the message has
+    /// been altered. When preserving the format, the tag goes before any
+    /// whitespace which ends the message.
+    pub fn tag_length(mut self, tag_length: usize) -> Self {
+        self.config.tag_length = tag_length;
+        self
+    }
+
+    pub fn build(self) -> Result<CipherConfig, ConfigError> {
+        let mut config = self.config;
+        if let Some(pad) = self.pad {
+            config.alphabet = config.alphabet.with_pad(pad)?;
+        }
+        if config.padding == Padding::LengthMarked && config.pad_length() > config.alphabet.size() {
+            return Err(ConfigError::GroupTooLarge {
+                group: config.pad_length(),
+                alphabet: config.alphabet.size(),
+            });
+        }
+        if config.alphabet.value(config.separator).is_some() {
+            return Err(ConfigError::SeparatorInAlphabet(config.separator));
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_separation() {
+        let config = CipherConfig::default();
+        assert_eq!(
+            config.separate("abcdefghijklmnopqrstuvwxyz".chars()),
+            "abcde fghij klmno pqrst uvwxy z"
+        );
+    }
+
+    #[test]
+    fn test_lines() {
+        let config = CipherConfig::builder()
+            .group_size(4)
+            .separator('-')
+            .groups_per_line(2)
+            .build()
+            .unwrap();
+        assert_eq!(
+            config.separate("abcdefghijklmnopqrst".chars()),
+            "abcd-efgh\nijkl-mnop\nqrst"
+        );
+    }
+
+    #[test]
+    fn test_ungrouped() {
+        let config = CipherConfig::builder().group_size(0).build().unwrap();
+        assert_eq!(config.separate("abcdefghijkl".chars()), "abcdefghijkl");
+        assert_eq!(config.pad_length(), 1);
+    }
+
+    fn length_marked() -> CipherConfig {
+        CipherConfig::builder()
+            .padding(Padding::LengthMarked)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_length_marked_padding() {
+        let config = length_marked();
+        assert_eq!(config.padding_for(3), &[2, 2]);
+        assert_eq!(config.padding_for(4), &[1]);
+        assert_eq!(config.padding_for(5), &[5, 5, 5, 5, 5]);
+        for len in 0..12 {
+            let mut values = vec![24; len];
+            values.extend(config.padding_for(len));
+            config.unpad(&mut values).unwrap();
+            assert_eq!(values.len(), len);
+        }
+    }
+
+    #[test]
+    fn test_malformed_padding() {
+        let config = length_marked();
+        for values in &[
+            vec![],
+            vec![1, 2, 3, 4],
+            vec![1, 2, 3, 4, 6],
+            vec![1, 2, 3, 2, 3],
+            vec![1, 2, 3, 4, 24],
+        ] {
+            assert_eq!(
+                config.unpad(&mut values.clone()),
+                Err(CipherError::MalformedPadding),
+                "{:?}",
+                values
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(
+            CipherConfig::builder().pad('!').build(),
+            Err(ConfigError::Alphabet(AlphabetError::UnknownSymbol('!')))
+        );
+        assert_eq!(
+            CipherConfig::builder().separator('q').build(),
+            Err(ConfigError::SeparatorInAlphabet('q'))
+        );
+        assert_eq!(
+            CipherConfig::builder()
+                .group_size(30)
+                .padding(Padding::LengthMarked)
+                .build(),
+            Err(ConfigError::GroupTooLarge {
+                group: 30,
+                alphabet: 26
+            })
+        );
+    }
+}
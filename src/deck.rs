@@ -1,18 +1,28 @@
-use crate::card::{Card, CardConversionError, JOKER_A, JOKER_B};
+use crate::card::{Card, CardConversionError, CardFormat, Joker};
+use crate::fingerprint::Fingerprint;
+use crate::rank::{Rank, RankEncoding, RankError};
 use crate::textbyte::textbyte;
-use lazy_static::lazy_static;
-use regex::Regex;
+use crate::trace::{Observer, Step};
+use rand::{CryptoRng, Rng};
 use std::convert::TryFrom;
 use std::fmt;
 use std::iter::FromIterator;
 use std::str::FromStr;
 use thiserror::Error;
 
-pub(crate) const DEFAULT_DECK_SIZE: usize = 54;
+mod dice;
+mod parse;
+mod shuffle;
+mod validation;
+pub use dice::{DiceError, DiceShuffle};
+pub use parse::TokenError;
+pub use shuffle::{
+    expected_rising_sequences, riffle_variation_distance, ShuffleMethod, UnknownMethod,
+};
+pub(crate) use validation::card_name;
+pub use validation::DeckProblems;
 
-lazy_static! {
-    static ref DECK_RE: Regex = Regex::new(r"(?i)[\djqkab]{1,2}[cdhsj♣♦♥♠♧♢♡♤]").unwrap();
-}
+pub(crate) const DEFAULT_DECK_SIZE: usize = 54;
 
 fn is_joker<const DECK_SIZE: usize>(v: u8) -> bool {
     let v = v as usize;
@@ -23,12 +33,37 @@ fn is_joker<const DECK_SIZE: usize>(v: u8) -> bool {
 pub enum DeckError {
     #[error("could not parse a card")]
     ParseCard(#[from] CardConversionError),
-    #[error("wrong number of cards in deck; need DECK_SIZE")]
-    WrongNumber,
-    #[error("each card in a deck must be unique")]
-    NotUnique,
-    #[error("cards in a deck must range from 1..=DECK_SIZE")]
-    OutOfBounds,
+    #[error("{0}")]
+    WrongNumber(DeckProblems),
+    #[error("{0}")]
+    NotUnique(DeckProblems),
+    #[error("{0}")]
+    OutOfBounds(DeckProblems),
+    #[error("could not parse a deck rank")]
+    ParseRank(#[from] RankError),
+    #[error(
+        "rank {rank} is too large: a deck of {deck_size} cards has a rank less than {deck_size}!"
+    )]
+    RankTooLarge { rank: Rank, deck_size: usize },
+    #[error("{}", parse::describe(.0))]
+    Unrecognized(Vec<TokenError>),
+}
+
+/// Optional variations on passphrase keying.
+///
+/// The default options are the basic keying algorithm, as implemented by
+/// Schneier's reference code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct KeyingOptions {
+    /// Use the final two letters of the passphrase to set the positions of
+    /// the jokers, as suggested by Schneier's specification.
+    ///
+    /// After keying with every letter, joker A is moved to lie directly below
+    /// the card counted out by the penultimate letter, then joker B is moved
+    /// to lie directly below the card counted out by the final letter. Cards
+    /// are counted from the top of the deck with the moving joker set aside.
+    /// Passphrases of fewer than two letters skip this step.
+    pub place_jokers: bool,
 }
 
 #[derive(Clone)]
@@ -47,11 +82,7 @@ impl<const DECK_SIZE: usize> fmt::Debug for Deck<DECK_SIZE> {
 
 impl<const DECK_SIZE: usize> fmt::Display for Deck<DECK_SIZE> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for (idx, card) in self.cards().enumerate() {
-            let space = if idx == 0 { "" } else { " " };
-            write!(f, "{}{}", space, card)?;
-        }
-        Ok(())
+        f.write_str(&self.format(CardFormat::Symbols))
     }
 }
 
@@ -64,6 +95,11 @@ impl<const DECK_SIZE: usize> PartialEq for Deck<DECK_SIZE> {
 impl<const DECK_SIZE: usize> Eq for Deck<DECK_SIZE> {}
 
 impl<const DECK_SIZE: usize> Deck<DECK_SIZE> {
+    /// The value of joker A: the second-highest card in the deck.
+    pub const JOKER_A: u8 = (DECK_SIZE - 1) as u8;
+    /// The value of joker B: the highest card in the deck.
+    pub const JOKER_B: u8 = DECK_SIZE as u8;
+
     /// Generate a new deck in sorted order
     pub fn new() -> Deck<DECK_SIZE> {
         let mut cards = [0; DECK_SIZE];
@@ -74,29 +110,170 @@ impl<const DECK_SIZE: usize> Deck<DECK_SIZE> {
     }
 
     /// Generate a deck from a passphrase to create the initial deck ordering.
+    ///
+    /// On decks too small to count out every letter, the count for each
+    /// letter wraps around the non-bottom cards.
     pub fn from_passphrase(phrase: &str) -> Deck<DECK_SIZE> {
+        Deck::from_passphrase_with(phrase, KeyingOptions::default())
+    }
+
+    /// Generate a deck from a passphrase, using a variant of the keying algorithm.
+    pub fn from_passphrase_with(phrase: &str, options: KeyingOptions) -> Deck<DECK_SIZE> {
+        Deck::from_passphrase_observed(phrase, options, &mut ())
+    }
+
+    /// Generate a deck from a passphrase, showing the deck to `observer` after every step.
+    pub fn from_passphrase_observed(
+        phrase: &str,
+        options: KeyingOptions,
+        observer: &mut impl Observer<DECK_SIZE>,
+    ) -> Deck<DECK_SIZE> {
         let mut deck = Deck::new();
-        for ch in textbyte(phrase) {
-            deck.push(JOKER_A, 1);
-            deck.push(JOKER_B, 2);
-            deck.triple_cut(JOKER_A, JOKER_B);
-            deck.count_cut(None);
-            deck.count_cut(Some(ch));
-        }
+        deck.key_observed(phrase, options, observer);
         deck
     }
 
+    /// Continue keying this deck with the letters of a passphrase.
+    pub fn key(&mut self, phrase: &str, options: KeyingOptions) {
+        self.key_observed(phrase, options, &mut ());
+    }
+
+    /// Continue keying this deck, showing it to `observer` after every step.
+    pub fn key_observed(
+        &mut self,
+        phrase: &str,
+        options: KeyingOptions,
+        observer: &mut impl Observer<DECK_SIZE>,
+    ) {
+        self.key_values_observed(textbyte(phrase), observer);
+        if options.place_jokers {
+            let letters: Vec<u8> = textbyte(phrase).collect();
+            if let [.., a, b] = letters[..] {
+                for (joker, n) in [(Joker::A, a), (Joker::B, b)] {
+                    self.place(Self::joker(joker), n);
+                    observer.observe(Step::PlaceJoker { joker, n }, self);
+                }
+            }
+        }
+    }
+
+    /// perform the per-letter keying steps for each of a sequence of letter values
+    pub(crate) fn key_values(&mut self, values: impl IntoIterator<Item = u8>) {
+        self.key_values_observed(values, &mut ());
+    }
+
+    fn key_values_observed(
+        &mut self,
+        values: impl IntoIterator<Item = u8>,
+        observer: &mut impl Observer<DECK_SIZE>,
+    ) {
+        for value in values {
+            self.step_observed(observer);
+            self.count_cut(Some((value as usize % (DECK_SIZE - 1)) as u8));
+            observer.observe(Step::LetterCut { letter: value }, self);
+        }
+    }
+
+    /// the value of a joker in this deck
+    pub(crate) fn joker(joker: Joker) -> u8 {
+        match joker {
+            Joker::A => Self::JOKER_A,
+            Joker::B => Self::JOKER_B,
+        }
+    }
+
+    /// move a card to lie directly below the `n`th other card from the top
+    ///
+    /// `n` wraps around the other cards; 0 places the card on top.
+    pub(crate) fn place<T>(&mut self, card: T, n: u8)
+    where
+        T: Into<u8>,
+    {
+        let card = card.into();
+        let idx = self.find(card);
+        let dest_idx = n as usize % (DECK_SIZE - 1);
+        if dest_idx < idx {
+            self.0.copy_within(dest_idx..idx, dest_idx + 1);
+        } else {
+            self.0.copy_within(idx + 1..=dest_idx, idx);
+        }
+        self.0[dest_idx] = card;
+    }
+
+    /// each joker, and how far it is pushed, in each round of the algorithm
+    const PUSHES: [(Joker, usize); 2] = [(Joker::A, 1), (Joker::B, 2)];
+
+    /// perform the four steps of a single round of the algorithm
+    ///
+    /// This moves both jokers, performs the triple cut, then the count cut,
+    /// leaving the deck ready to find an output card.
+    ///
+    /// The deck is shown to `observer` after each step.
+    ///
+    /// Returns whether each of jokers A and B was pushed from the bottom of
+    /// the deck, which is needed to undo the round with [`Deck::unstep`].
+    pub(crate) fn step_observed(&mut self, observer: &mut impl Observer<DECK_SIZE>) -> [bool; 2] {
+        let mut from_bottom = [false; 2];
+        for (idx, (joker, by)) in Self::PUSHES.iter().copied().enumerate() {
+            let card = Self::joker(joker);
+            from_bottom[idx] = self.0[DECK_SIZE - 1] == card;
+            self.push(card, by);
+            observer.observe(Step::Push { joker, by }, self);
+        }
+        self.triple_cut(Self::JOKER_A, Self::JOKER_B);
+        observer.observe(Step::TripleCut, self);
+        self.count_cut(None);
+        observer.observe(Step::CountCut, self);
+        from_bottom
+    }
+
+    /// Undo a single round of the algorithm.
+    ///
+    /// `from_bottom` must be as returned by the round being undone.
+    pub(crate) fn unstep(&mut self, from_bottom: [bool; 2]) {
+        self.uncount_cut(None);
+        self.untriple_cut(Self::JOKER_A, Self::JOKER_B);
+        for (idx, (joker, by)) in Self::PUSHES.iter().copied().enumerate().rev() {
+            self.unpush(Self::joker(joker), by, from_bottom[idx]);
+        }
+    }
+
+    /// a short, stable digest of the order of the cards
+    ///
+    /// Compare fingerprints to confirm that two decks are the same without
+    /// reading out every card. A fingerprint lets a passphrase be guessed
+    /// offline, so keep it as secret as the deck.
+    pub fn fingerprint(&self) -> Fingerprint {
+        Fingerprint::of(&self.0)
+    }
+
+    /// the values of the cards, from top to bottom
+    pub(crate) fn values(&self) -> &[u8] {
+        &self.0
+    }
+
     pub fn cards(&self) -> impl '_ + Iterator<Item = Card> {
         self.0
             .iter()
             .map(|v| Card::try_from(*v).expect("cards in decks should always be valid"))
     }
 
+    /// Generate a uniformly random deck.
+    pub fn random_with<R: Rng + CryptoRng>(rng: &mut R) -> Deck<DECK_SIZE> {
+        let mut deck = Deck::new();
+        deck.shuffle_with(rng);
+        deck
+    }
+
     pub fn shuffle(&mut self) {
+        self.shuffle_with(&mut rand::thread_rng());
+    }
+
+    /// Shuffle this deck into a uniformly random order, using the given source of randomness.
+    pub fn shuffle_with<R: Rng + CryptoRng>(&mut self, rng: &mut R) {
         use rand::seq::SliceRandom;
 
-        let mut rng = rand::thread_rng();
-        self.0.shuffle(&mut rng);
+        self.0.shuffle(rng);
     }
 
     fn find<T>(&self, card: T) -> usize
@@ -119,7 +296,7 @@ impl<const DECK_SIZE: usize> Deck<DECK_SIZE> {
     {
         let n = n % DECK_SIZE;
         let idx = self.find(card);
-        let mut next = self.0.clone();
+        let mut next = self.0;
         if idx + n >= DECK_SIZE {
             // wrap
             let dest_idx = (idx + n) % (DECK_SIZE - 1);
@@ -133,6 +310,38 @@ impl<const DECK_SIZE: usize> Deck<DECK_SIZE> {
         self.0 = next;
     }
 
+    /// undo pushing the given card down by n spaces
+    ///
+    /// A card pushed down from the bottom of the deck continues from the top,
+    /// so it lands exactly where it would have had it been pushed from the
+    /// top instead. When the card is `n` spaces from the top, `from_bottom`
+    /// chooses where it returns to; otherwise it is ignored.
+    pub fn unpush<T>(&mut self, card: T, n: usize, from_bottom: bool)
+    where
+        T: Into<u8>,
+    {
+        let n = n % DECK_SIZE;
+        let card = card.into();
+        let idx = self.find(card);
+        let ambiguous = idx == n && 0 < n && n < DECK_SIZE - 1;
+        let source_idx = if idx >= n && !(ambiguous && from_bottom) {
+            // no wrap
+            idx - n
+        } else if idx == 0 {
+            // wrapped all the way from the bottom to the top
+            DECK_SIZE - 1
+        } else {
+            // wrap
+            idx + DECK_SIZE - 1 - n
+        };
+        if source_idx < idx {
+            self.0.copy_within(source_idx..idx, source_idx + 1);
+        } else {
+            self.0.copy_within(idx + 1..=source_idx, idx);
+        }
+        self.0[source_idx] = card;
+    }
+
     /// swap the cards before the first and second found
     ///
     /// the cards specified must not be identical. The order in which the cards
@@ -166,10 +375,21 @@ impl<const DECK_SIZE: usize> Deck<DECK_SIZE> {
         self.0 = next;
     }
 
-    /// excluding the bottom card of the deck, cut the deck at a position
-    /// specified by the bottom card
-    pub fn count_cut(&mut self, override_idx: Option<u8>) {
-        let idx = match override_idx {
+    /// undo the triple cut
+    ///
+    /// The triple cut is its own inverse, as the cards between the two
+    /// specified are unmoved and the rest swap back.
+    pub fn untriple_cut<T1, T2>(&mut self, card0: T1, card1: T2)
+    where
+        T1: Into<u8>,
+        T2: Into<u8>,
+    {
+        self.triple_cut(card0, card1);
+    }
+
+    /// the position at which to count cut
+    fn count_cut_idx(&self, override_idx: Option<u8>) -> usize {
+        match override_idx {
             Some(oi) => oi as usize,
             None => {
                 let mut idx = self.0[DECK_SIZE - 1] as usize;
@@ -179,8 +399,28 @@ impl<const DECK_SIZE: usize> Deck<DECK_SIZE> {
                 }
                 idx
             }
-        };
+        }
+    }
+
+    /// excluding the bottom card of the deck, cut the deck at a position
+    /// specified by the bottom card
+    pub fn count_cut(&mut self, override_idx: Option<u8>) {
+        let idx = self.count_cut_idx(override_idx);
+        self.cut(idx);
+    }
+
+    /// undo the count cut
+    ///
+    /// The bottom card is unmoved by the count cut, so it still specifies
+    /// where the deck was cut.
+    pub fn uncount_cut(&mut self, override_idx: Option<u8>) {
+        let idx = self.count_cut_idx(override_idx);
+        self.cut(DECK_SIZE - 1 - idx);
+    }
 
+    /// excluding the bottom card of the deck, move the top `idx` cards to
+    /// lie above it
+    fn cut(&mut self, idx: usize) {
         let range_b_len = DECK_SIZE - idx - 1;
         let mut next = [0; DECK_SIZE];
         next[..range_b_len].copy_from_slice(&self.0[idx..DECK_SIZE - 1]);
@@ -193,6 +433,12 @@ impl<const DECK_SIZE: usize> Deck<DECK_SIZE> {
     ///
     /// range: `1..=DECK_SIZE`
     pub fn output(&self) -> Option<u8> {
+        let card = self.output_card();
+        (!is_joker::<DECK_SIZE>(card)).then_some(card)
+    }
+
+    /// the card counted down to by the top card, which may be a joker
+    pub(crate) fn output_card(&self) -> u8 {
         let idx = {
             let mut idx = self.0[0] as usize;
             // the jokers should both have the same value
@@ -201,17 +447,60 @@ impl<const DECK_SIZE: usize> Deck<DECK_SIZE> {
             }
             idx
         };
-        let card = self.0[idx];
-        (!is_joker::<DECK_SIZE>(card)).then(move || card)
+        self.0[idx]
+    }
+
+    /// the index of this deck among all orderings of its cards
+    ///
+    /// The sorted deck has rank 0.
+    pub fn to_rank(&self) -> Rank {
+        let mut rank = Rank::zero();
+        for (idx, &card) in self.0.iter().enumerate() {
+            let smaller_after = self.0[idx + 1..].iter().filter(|&&c| c < card).count();
+            rank.mul_add((DECK_SIZE - idx) as u32, smaller_after as u32);
+        }
+        rank
+    }
+
+    /// the deck with the given index among all orderings of its cards
+    pub fn from_rank(rank: &Rank) -> Result<Deck<DECK_SIZE>, DeckError> {
+        if *rank >= Rank::factorial(DECK_SIZE) {
+            return Err(DeckError::RankTooLarge {
+                rank: rank.clone(),
+                deck_size: DECK_SIZE,
+            });
+        }
+        let mut rank = rank.clone();
+        let mut smaller_after = [0; DECK_SIZE];
+        for idx in (0..DECK_SIZE).rev() {
+            smaller_after[idx] = rank.div_rem((DECK_SIZE - idx) as u32) as usize;
+        }
+        let mut remaining: Vec<u8> = (1..=DECK_SIZE as u8).collect();
+        let mut cards = [0; DECK_SIZE];
+        for (card, &smaller) in cards.iter_mut().zip(smaller_after.iter()) {
+            *card = remaining.remove(smaller);
+        }
+        Ok(Deck(cards))
+    }
+
+    /// Encode the rank of this deck as text which [`MaybeDeck`] can parse.
+    pub fn to_rank_string(&self, encoding: RankEncoding) -> String {
+        self.to_rank()
+            .encode(encoding, Rank::width(encoding, DECK_SIZE))
     }
 
     pub fn to_ascii_string(&self) -> String {
+        self.format(CardFormat::Ascii)
+    }
+
+    /// Render this deck in the given format, from top to bottom.
+    pub fn format(&self, format: CardFormat) -> String {
         let mut out = String::with_capacity(4 * DECK_SIZE);
         for (idx, card) in self.cards().enumerate() {
             if idx != 0 {
-                out.push(' ');
+                out.push_str(format.separator());
             }
-            out.push_str(&card.to_ascii_string());
+            out.push_str(&card.format(format));
         }
         out
     }
@@ -230,15 +519,12 @@ pub struct MaybeDeck<const DECK_SIZE: usize = DEFAULT_DECK_SIZE>(Vec<u8>);
 impl FromStr for MaybeDeck<DEFAULT_DECK_SIZE> {
     type Err = DeckError;
 
+    /// Parse a list of cards, or the rank of a deck with its encoding prefix.
+    ///
+    /// Anything which is not a card is ignored; see
+    /// [`MaybeDeck::parse_strict`] for a parser which rejects it.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        const DECK_SIZE: usize = DEFAULT_DECK_SIZE;
-
-        let cards: Vec<Card<DECK_SIZE>> = DECK_RE
-            .captures_iter(s)
-            .take(DECK_SIZE + 1)
-            .map(|caps| Card::from_str(&caps[0]))
-            .collect::<Result<Vec<_>, _>>()?;
-        Ok(Self(cards.iter().map(|card| card.into()).collect()))
+        MaybeDeck::parse_lenient(s)
     }
 }
 
@@ -250,31 +536,33 @@ where
     where
         I: IntoIterator<Item = T>,
     {
-        MaybeDeck(
-            iter.into_iter()
-                .take(DECK_SIZE + 1)
-                .map(Into::into)
-                .collect(),
-        )
+        MaybeDeck(iter.into_iter().map(Into::into).collect())
     }
 }
 
 impl MaybeDeck {
+    /// Parse the rank of a deck, if `s` begins with an encoding prefix.
+    fn parse_rank(s: &str) -> Option<Result<MaybeDeck, DeckError>> {
+        let (prefix, _) = s.trim().split_once(':')?;
+        prefix.trim().parse::<RankEncoding>().ok()?;
+        Some(
+            s.parse()
+                .map_err(DeckError::from)
+                .and_then(|rank| Deck::<DEFAULT_DECK_SIZE>::from_rank(&rank))
+                .map(|deck| MaybeDeck(deck.0.to_vec())),
+        )
+    }
+
     pub fn check<const DECK_SIZE: usize>(self) -> Result<Deck<DECK_SIZE>, DeckError> {
-        if self.0.len() != DECK_SIZE {
-            return Err(DeckError::WrongNumber);
+        let problems = DeckProblems::examine(&self.0, DECK_SIZE);
+        if problems.actual != DECK_SIZE {
+            return Err(DeckError::WrongNumber(problems));
         }
-        // it is entirely possible that our deck is unordered. In order to keep
-        // doing our checks without forcibly ordering the deck, let's copy
-        // the arrangement for mutation.
-        let mut scards = self.0.clone();
-        scards.sort();
-        scards.dedup();
-        if scards.len() != DECK_SIZE {
-            return Err(DeckError::NotUnique);
+        if !problems.out_of_bounds.is_empty() {
+            return Err(DeckError::OutOfBounds(problems));
         }
-        if scards[0] != 1 || scards[scards.len() - 1] != (DECK_SIZE as u8) {
-            return Err(DeckError::OutOfBounds);
+        if !problems.duplicates.is_empty() {
+            return Err(DeckError::NotUnique(problems));
         }
 
         let mut arr = [0; DECK_SIZE];
@@ -383,6 +671,125 @@ mod small_deck_tests {
         assert_eq!(deck.0, [1, 2, 3, 4, 5, 6, 7, 8]);
     }
 
+    #[test]
+    fn test_jokers() {
+        assert_eq!(Deck::<DECK_SIZE>::JOKER_A, 7);
+        assert_eq!(Deck::<DECK_SIZE>::JOKER_B, 8);
+        assert_eq!(
+            Deck::<DEFAULT_DECK_SIZE>::JOKER_A,
+            u8::from(crate::card::JOKER_A)
+        );
+        assert_eq!(
+            Deck::<DEFAULT_DECK_SIZE>::JOKER_B,
+            u8::from(crate::card::JOKER_B)
+        );
+    }
+
+    #[test]
+    fn test_unpush_ambiguity() {
+        // pushing from the top and from the bottom land in the same place
+        let mut from_top = Deck::new();
+        from_top.push(1, 1);
+        let mut from_bottom = Deck::new();
+        from_bottom.push(8, 1);
+        assert_eq!(from_top.0, [2, 1, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(from_bottom.0, [1, 8, 2, 3, 4, 5, 6, 7]);
+        from_top.unpush(1, 1, false);
+        from_bottom.unpush(8, 1, true);
+        assert_eq!(from_top, Deck::new());
+        assert_eq!(from_bottom, Deck::new());
+    }
+
+    #[test]
+    fn test_inverses() {
+        for phrase in &["", "inverse", "cryptonomicon"] {
+            let deck = Deck::<DECK_SIZE>::from_passphrase(phrase);
+            for card in 1..=DECK_SIZE as u8 {
+                for n in 0..DECK_SIZE {
+                    let mut pushed = deck.clone();
+                    pushed.push(card, n);
+                    pushed.unpush(card, n, deck.find(card) == DECK_SIZE - 1);
+                    assert_eq!(pushed, deck, "push({}, {}) of {}", card, n, deck);
+                }
+                let mut cut = deck.clone();
+                cut.triple_cut(card, card % DECK_SIZE as u8 + 1);
+                cut.untriple_cut(card, card % DECK_SIZE as u8 + 1);
+                assert_eq!(cut, deck);
+            }
+            for idx in (0..DECK_SIZE as u8).map(Some).chain(Some(None)) {
+                let mut cut = deck.clone();
+                cut.count_cut(idx);
+                cut.uncount_cut(idx);
+                assert_eq!(cut, deck, "count_cut({:?}) of {}", idx, deck);
+            }
+        }
+    }
+
+    #[test]
+    fn test_place() {
+        let mut deck = Deck::new();
+        deck.place(7, 2);
+        assert_eq!(deck.0, [1, 2, 7, 3, 4, 5, 6, 8]);
+        deck.place(7, 5);
+        assert_eq!(deck.0, [1, 2, 3, 4, 5, 7, 6, 8]);
+        deck.place(8, 0);
+        assert_eq!(deck.0, [8, 1, 2, 3, 4, 5, 7, 6]);
+        deck.place(8, 7);
+        assert_eq!(deck.0, [8, 1, 2, 3, 4, 5, 7, 6]);
+    }
+
+    #[test]
+    fn test_check_problems() {
+        match [1, 2, 3, 4, 5, 6, 7, 7]
+            .iter()
+            .copied()
+            .collect::<MaybeDeck>()
+            .check::<DECK_SIZE>()
+        {
+            Err(DeckError::NotUnique(problems)) => {
+                assert_eq!(problems.duplicates, vec![(7, vec![7, 8])]);
+                assert_eq!(problems.missing, vec![8]);
+                assert!(problems.to_string().ends_with("with BJ"));
+            }
+            other => panic!("expected a duplicate card; got {:?}", other),
+        }
+        assert!(matches!(
+            [1, 2, 3]
+                .iter()
+                .copied()
+                .collect::<MaybeDeck>()
+                .check::<DECK_SIZE>(),
+            Err(DeckError::WrongNumber(_))
+        ));
+        match (1..=60).collect::<MaybeDeck>().check::<DECK_SIZE>() {
+            Err(DeckError::WrongNumber(problems)) => assert_eq!(problems.actual, 60),
+            other => panic!("expected the wrong number of cards; got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_rank() {
+        assert!(Deck::<DECK_SIZE>::new().to_rank().is_zero());
+        let reversed = (1..=DECK_SIZE as u8)
+            .rev()
+            .collect::<MaybeDeck>()
+            .check::<DECK_SIZE>()
+            .unwrap();
+        // 8! - 1
+        assert_eq!(reversed.to_rank().to_string(), "40319");
+        assert_eq!(Deck::from_rank(&reversed.to_rank()).unwrap(), reversed);
+        let err = Deck::<DECK_SIZE>::from_rank(&Rank::factorial(DECK_SIZE)).unwrap_err();
+        assert!(matches!(err, DeckError::RankTooLarge { deck_size: 8, .. }));
+        assert_eq!(
+            err.to_string(),
+            "rank 40320 is too large: a deck of 8 cards has a rank less than 8!"
+        );
+        for n in 0..200 {
+            let rank = Rank::from(n * 197);
+            assert_eq!(Deck::<DECK_SIZE>::from_rank(&rank).unwrap().to_rank(), rank);
+        }
+    }
+
     #[test]
     fn test_parse() {
         let deck = str::parse::<MaybeDeck>("ac 2C 3c 4C 5c 6C 7c 8C").unwrap();
@@ -395,11 +802,69 @@ mod small_deck_tests {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_default_keying_options() {
+        for phrase in &["", "foo", "cryptonomicon"] {
+            assert_eq!(
+                Deck::<DECK_SIZE>::from_passphrase_with(phrase, KeyingOptions::default()),
+                Deck::<DECK_SIZE>::from_passphrase(phrase),
+            );
+        }
+    }
+
+    #[test]
+    fn test_joker_placement() {
+        let options = KeyingOptions { place_jokers: true };
+        // o == 15, n == 14
+        let deck = Deck::<DECK_SIZE>::from_passphrase_with("cryptonomicon", options);
+        let basic = Deck::<DECK_SIZE>::from_passphrase("cryptonomicon");
+        assert_ne!(deck, basic);
+        assert_eq!(deck.find(Deck::<DECK_SIZE>::JOKER_B), 14);
+        let others = |d: &Deck<DECK_SIZE>| {
+            d.0.iter()
+                .copied()
+                .filter(|&c| !is_joker::<DECK_SIZE>(c))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(others(&deck), others(&basic));
+        // too short to place the jokers
+        assert_eq!(
+            Deck::<DECK_SIZE>::from_passphrase_with("f", options),
+            Deck::<DECK_SIZE>::from_passphrase("f"),
+        );
+    }
+
     // This is not in fact dead code; it's just that it's only used within a test which
     // isn't showing up for _reasons_?
     #[allow(dead_code)]
     const DECK_SIZE: usize = DEFAULT_DECK_SIZE;
 
+    #[test]
+    fn test_parse_rank() {
+        let deck = Deck::<DECK_SIZE>::from_passphrase("rank");
+        for encoding in &[
+            RankEncoding::Base26,
+            RankEncoding::Base32,
+            RankEncoding::Decimal,
+        ] {
+            let text = deck.to_rank_string(*encoding);
+            let parsed: Deck = text.parse::<MaybeDeck>().unwrap().check().unwrap();
+            assert_eq!(parsed, deck);
+        }
+        assert_eq!(deck.to_rank_string(RankEncoding::Base26).len(), 4 + 51 + 10);
+    }
+
+    #[test]
+    fn test_seeded_shuffle() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let deck = Deck::<DECK_SIZE>::random_with(&mut StdRng::seed_from_u64(7));
+        let mut again = Deck::<DECK_SIZE>::new();
+        again.shuffle_with(&mut StdRng::seed_from_u64(7));
+        assert_eq!(deck, again);
+        assert_ne!(deck, Deck::random_with(&mut StdRng::seed_from_u64(8)));
+    }
+
     #[test]
     fn test_unkeyed() {
         let d = Deck::<DECK_SIZE>::new();
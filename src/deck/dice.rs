@@ -0,0 +1,228 @@
+//! Shuffling a deck with entropy a person can verify: dice rolls or coin flips.
+//!
+//! The deck is shuffled by Fisher–Yates: for each position from the top, a
+//! card is chosen uniformly from those not yet placed and swapped into it.
+//! Each choice is made by reading just enough rolls as the digits of a
+//! number. If that number is too large to map evenly onto the choices, the
+//! rolls are discarded and more are read, so that no choice is more likely
+//! than any other.
+
+use super::Deck;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum DiceError {
+    #[error("dice need at least 2 sides; got {0}")]
+    TooFewSides(u8),
+    #[error("a d{sides} cannot roll {roll}")]
+    InvalidRoll { roll: u8, sides: u8 },
+    #[error("not enough rolls; at least {remaining} more are needed")]
+    NotEnough { remaining: usize },
+}
+
+/// A deck being shuffled by rolls of a die, one roll at a time.
+#[derive(Debug, Clone)]
+pub struct DiceShuffle<const DECK_SIZE: usize> {
+    deck: Deck<DECK_SIZE>,
+    sides: u8,
+    /// the position being filled
+    position: usize,
+    /// the rolls read for the current choice, as a number
+    value: u64,
+    /// the number of values the rolls read for the current choice could have made
+    range: u64,
+    /// how many rolls have been used, including those rejected
+    rolls: usize,
+    /// how many rolls have been rejected
+    rejected: usize,
+}
+
+impl<const DECK_SIZE: usize> DiceShuffle<DECK_SIZE> {
+    /// Begin shuffling a sorted deck with a die of the given number of sides.
+    ///
+    /// A coin is a die with two sides.
+    pub fn new(sides: u8) -> Result<DiceShuffle<DECK_SIZE>, DiceError> {
+        if sides < 2 {
+            return Err(DiceError::TooFewSides(sides));
+        }
+        Ok(DiceShuffle {
+            deck: Deck::new(),
+            sides,
+            position: 0,
+            value: 0,
+            range: 1,
+            rolls: 0,
+            rejected: 0,
+        })
+    }
+
+    /// how many cards remain to choose between for the current position
+    fn choices(&self) -> u64 {
+        (DECK_SIZE - self.position) as u64
+    }
+
+    /// how many rolls are needed to choose between `choices` options
+    fn rolls_for(&self, choices: u64) -> usize {
+        let mut range = 1;
+        let mut rolls = 0;
+        while range < choices {
+            range *= self.sides as u64;
+            rolls += 1;
+        }
+        rolls
+    }
+
+    /// Use a roll, in the range `1..=sides`.
+    ///
+    /// Rolls after the shuffle is complete are ignored.
+    pub fn push(&mut self, roll: u8) -> Result<(), DiceError> {
+        if roll == 0 || roll > self.sides {
+            return Err(DiceError::InvalidRoll {
+                roll,
+                sides: self.sides,
+            });
+        }
+        if self.is_complete() {
+            return Ok(());
+        }
+        self.rolls += 1;
+        self.value = self.value * self.sides as u64 + (roll - 1) as u64;
+        self.range *= self.sides as u64;
+
+        let choices = self.choices();
+        if self.range < choices {
+            return Ok(());
+        }
+        let limit = self.range - self.range % choices;
+        if self.value < limit {
+            let choice = self.position + (self.value % choices) as usize;
+            self.deck.0.swap(self.position, choice);
+            self.position += 1;
+        } else {
+            self.rejected += self.rolls_for(choices);
+        }
+        self.value = 0;
+        self.range = 1;
+        Ok(())
+    }
+
+    /// whether every card has been placed
+    ///
+    /// The final card has only one choice, so needs no rolls.
+    pub fn is_complete(&self) -> bool {
+        self.position + 1 >= DECK_SIZE
+    }
+
+    /// the fewest rolls which could complete the shuffle
+    ///
+    /// More are needed whenever rolls are rejected.
+    pub fn remaining(&self) -> usize {
+        if self.is_complete() {
+            return 0;
+        }
+        let current = self.rolls_for(self.choices()) - self.rolls_for(self.range);
+        let later: usize = (2..self.choices())
+            .map(|choices| self.rolls_for(choices))
+            .sum();
+        current + later
+    }
+
+    /// how many rolls have been used
+    pub fn rolls(&self) -> usize {
+        self.rolls
+    }
+
+    /// how many of the rolls used were rejected to avoid bias
+    pub fn rejected(&self) -> usize {
+        self.rejected
+    }
+
+    /// the shuffled deck, if it is complete
+    pub fn deck(&self) -> Result<Deck<DECK_SIZE>, DiceError> {
+        if self.is_complete() {
+            Ok(self.deck.clone())
+        } else {
+            Err(DiceError::NotEnough {
+                remaining: self.remaining(),
+            })
+        }
+    }
+}
+
+impl<const DECK_SIZE: usize> Deck<DECK_SIZE> {
+    /// Generate a uniformly random deck from rolls of a die with `sides` sides.
+    ///
+    /// Rolls are in the range `1..=sides`; a coin is a die with two sides.
+    /// If there are too few rolls, the error reports the fewest more which
+    /// could be enough. Extra rolls are ignored.
+    pub fn from_dice(
+        sides: u8,
+        rolls: impl IntoIterator<Item = u8>,
+    ) -> Result<Deck<DECK_SIZE>, DiceError> {
+        let mut shuffle = DiceShuffle::new(sides)?;
+        for roll in rolls {
+            if shuffle.is_complete() {
+                break;
+            }
+            shuffle.push(roll)?;
+        }
+        shuffle.deck()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_remaining() {
+        let shuffle = DiceShuffle::<8>::new(2).unwrap();
+        // 8 choices: 3 flips; 7, 6, 5: 3 flips; 4, 3: 2 flips; 2: 1 flip
+        assert_eq!(shuffle.remaining(), 3 * 4 + 2 * 2 + 1);
+        assert_eq!(
+            Deck::<8>::from_dice(2, vec![1, 2]),
+            Err(DiceError::NotEnough { remaining: 15 })
+        );
+    }
+
+    #[test]
+    fn test_d6_deck() {
+        let shuffle = DiceShuffle::<54>::new(6).unwrap();
+        let minimum = shuffle.remaining();
+        let mut rng = StdRng::seed_from_u64(16);
+        let rolls: Vec<u8> = (0..2 * minimum).map(|_| rng.gen_range(1..=6)).collect();
+        let deck = Deck::<54>::from_dice(6, rolls.iter().copied()).unwrap();
+        let mut cards = deck.0;
+        cards.sort_unstable();
+        assert_eq!(cards, Deck::<54>::new().0);
+        assert_eq!(Deck::from_dice(6, rolls), Ok(deck));
+    }
+
+    #[test]
+    fn test_rejection() {
+        let mut shuffle = DiceShuffle::<3>::new(2).unwrap();
+        // 3 choices need 2 flips, which make 4 values; the last is rejected
+        shuffle.push(2).unwrap();
+        shuffle.push(2).unwrap();
+        assert_eq!(shuffle.rejected(), 2);
+        assert_eq!(shuffle.remaining(), 3);
+        shuffle.push(1).unwrap();
+        shuffle.push(2).unwrap();
+        shuffle.push(2).unwrap();
+        assert!(shuffle.is_complete());
+        assert_eq!(shuffle.deck().unwrap(), Deck([2, 3, 1]));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(matches!(
+            DiceShuffle::<54>::new(1),
+            Err(DiceError::TooFewSides(1))
+        ));
+        assert_eq!(
+            Deck::<54>::from_dice(6, vec![7]),
+            Err(DiceError::InvalidRoll { roll: 7, sides: 6 })
+        );
+    }
+}
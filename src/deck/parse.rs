@@ -0,0 +1,302 @@
+//! Parsing a list of cards.
+//!
+//! The lenient parser behind [`MaybeDeck`]'s `FromStr` picks every card it
+//! can find out of its input and ignores the rest, so a typo can silently
+//! drop a card. It reads bare numbers as cards only when it finds no other
+//! cards, so that a label such as `Deck 1:` is not mistaken for one. The
+//! strict parser splits its input into tokens at whitespace, commas, and
+//! color codes, and rejects every token which is not a card.
+
+use super::{DeckError, MaybeDeck};
+use crate::card::{Card, CardConversionError, JokerNames};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::ops::Range;
+use thiserror::Error;
+
+/// a card written as its English name, its rank and suit, or a playing card
+/// glyph
+const CARD_PATTERN: &str = concat!(
+    r"\b(?:ace|two|three|four|five|six|seven|eight|nine|ten|jack|queen|king)\s+of\s+",
+    r"(?:club|diamond|heart|spade)s?\b|\bjoker\s+[ab]\b|",
+    r"[\djqkabt]{1,2}[cdhsj♣♦♥♠♧♢♡♤]|[\x{1F0A1}-\x{1F0DE}]",
+);
+
+lazy_static! {
+    static ref DECK_RE: Regex = lenient_re(&JokerNames::default());
+    /// a card written as its value
+    static ref NUMBER_RE: Regex = Regex::new(r"\b\d{1,2}\b").unwrap();
+    static ref ANSI_RE: Regex = Regex::new(r"\x1b\[[\x30-\x3f]*[\x20-\x2f]*[\x40-\x7e]").unwrap();
+}
+
+/// a pattern matching every card, and every joker name, in a string
+fn lenient_re(jokers: &JokerNames) -> Regex {
+    let mut names: Vec<_> = jokers.names().map(regex::escape).collect();
+    // prefer the longest name where one is a prefix of another
+    names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+    names.push(CARD_PATTERN.to_string());
+    Regex::new(&format!("(?i){}", names.join("|"))).expect("joker names are escaped")
+}
+
+/// A token which is not a card.
+#[derive(Error, Debug)]
+#[error("{text:?} at bytes {}..{} is not a card: {reason}", span.start, span.end)]
+pub struct TokenError {
+    /// the byte range of the token within the input
+    pub span: Range<usize>,
+    /// the text of the token
+    pub text: String,
+    /// why the token could not be parsed
+    #[source]
+    pub reason: CardConversionError,
+}
+
+/// describe every unrecognized token
+pub(super) fn describe(errors: &[TokenError]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || c == ','
+}
+
+/// split `s` into words, with their byte ranges
+///
+/// Words are separated by whitespace, commas, and ANSI escape codes.
+fn words(s: &str) -> Vec<(Range<usize>, &str)> {
+    let mut out = Vec::new();
+    let mut start = None;
+    let mut chars = s.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        let separator = if c == '\x1b' {
+            // skip a control sequence, such as a color, through its final byte
+            if chars.next_if(|&(_, c)| c == '[').is_some() {
+                while chars.next_if(|&(_, c)| !('@'..='~').contains(&c)).is_some() {}
+                chars.next();
+            }
+            true
+        } else {
+            is_separator(c)
+        };
+        match (separator, start) {
+            (true, Some(begin)) => {
+                out.push((begin..idx, &s[begin..idx]));
+                start = None;
+            }
+            (false, None) => start = Some(idx),
+            _ => {}
+        }
+    }
+    if let Some(begin) = start {
+        out.push((begin..s.len(), &s[begin..]));
+    }
+    out
+}
+
+/// split `s` into tokens which should each be a card, with their byte ranges
+///
+/// Most tokens are single words, but English names such as `Ace of Clubs`
+/// or `Joker A` span several.
+fn tokens(s: &str) -> Vec<(Range<usize>, &str)> {
+    let words = words(s);
+    let mut out = Vec::with_capacity(words.len());
+    let mut idx = 0;
+    while idx < words.len() {
+        let is = |offset: usize, word: &str| {
+            words
+                .get(idx + offset)
+                .is_some_and(|(_, w)| w.eq_ignore_ascii_case(word))
+        };
+        let len = if is(1, "of") && idx + 2 < words.len() {
+            3
+        } else if is(0, "joker") && idx + 1 < words.len() {
+            2
+        } else {
+            1
+        };
+        let span = words[idx].0.start..words[idx + len - 1].0.end;
+        out.push((span.clone(), &s[span]));
+        idx += len;
+    }
+    out
+}
+
+impl MaybeDeck {
+    /// Parse a list of cards, or the rank of a deck with its encoding prefix.
+    ///
+    /// Cards are separated by whitespace or commas, and may be written in any
+    /// [`CardFormat`](crate::card::CardFormat). Unlike the lenient
+    /// parser, every token must be a card; if any is not, the error lists
+    /// each one which is not.
+    pub fn parse_strict(s: &str) -> Result<MaybeDeck, DeckError> {
+        MaybeDeck::parse_strict_with(s, &JokerNames::default())
+    }
+
+    /// Parse strictly, recognizing the jokers by the given names.
+    pub fn parse_strict_with(s: &str, jokers: &JokerNames) -> Result<MaybeDeck, DeckError> {
+        if let Some(deck) = MaybeDeck::parse_rank(s) {
+            return deck;
+        }
+
+        let mut cards = Vec::new();
+        let mut errors = Vec::new();
+        for (span, text) in tokens(s) {
+            match Card::parse_with(text, jokers) {
+                Ok(card) => cards.push(u8::from(card)),
+                Err(reason) => errors.push(TokenError {
+                    span,
+                    text: text.to_string(),
+                    reason,
+                }),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(DeckError::Unrecognized(errors));
+        }
+        Ok(MaybeDeck(cards))
+    }
+
+    /// Parse a list of cards, ignoring anything which is not a card.
+    ///
+    /// Cards written as their values are only recognized if no card is
+    /// written any other way. This is the same as parsing with `FromStr`.
+    pub fn parse_lenient(s: &str) -> Result<MaybeDeck, DeckError> {
+        MaybeDeck::parse_lenient_with(s, &JokerNames::default())
+    }
+
+    /// Parse leniently, recognizing the jokers by the given names.
+    pub fn parse_lenient_with(s: &str, jokers: &JokerNames) -> Result<MaybeDeck, DeckError> {
+        if let Some(deck) = MaybeDeck::parse_rank(s) {
+            return deck;
+        }
+
+        let custom;
+        let re = if *jokers == JokerNames::default() {
+            &*DECK_RE
+        } else {
+            custom = lenient_re(jokers);
+            &custom
+        };
+        let s = ANSI_RE.replace_all(s, " ");
+        let mut found: Vec<_> = re.find_iter(&s).collect();
+        if found.is_empty() {
+            found = NUMBER_RE.find_iter(&s).collect();
+        }
+        let cards = found
+            .into_iter()
+            .map(|card| Card::parse_with(card.as_str(), jokers).map(u8::from))
+            .collect::<Result<_, _>>()?;
+        Ok(MaybeDeck(cards))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::CardFormat;
+    use crate::deck::Deck;
+
+    #[test]
+    fn test_tokens() {
+        let found = tokens(" ac,2c  3♣, ace of clubs");
+        assert_eq!(
+            found,
+            vec![
+                (1..3, "ac"),
+                (4..6, "2c"),
+                (8..12, "3♣"),
+                (14..26, "ace of clubs")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unrecognized() {
+        let err = MaybeDeck::parse_strict("AC Xh 2C 1O♣").unwrap_err();
+        let errors = match err {
+            DeckError::Unrecognized(errors) => errors,
+            _ => panic!("expected unrecognized tokens; got {:?}", err),
+        };
+        let spans: Vec<_> = errors
+            .iter()
+            .map(|error| (error.span.clone(), error.text.as_str()))
+            .collect();
+        assert_eq!(spans, vec![(3..5, "Xh"), (9..14, "1O♣")]);
+        assert!(MaybeDeck::parse_lenient("AC Xh 2C 1O♣").is_ok());
+        assert!(MaybeDeck::parse_strict("14♣").is_err());
+    }
+
+    #[test]
+    fn test_modes_agree() {
+        let deck: Deck = Deck::from_passphrase("strict");
+        for text in &[deck.to_string(), deck.to_ascii_string()] {
+            let strict: Deck = MaybeDeck::parse_strict(text).unwrap().check().unwrap();
+            let lenient: Deck = MaybeDeck::parse_lenient(text).unwrap().check().unwrap();
+            assert_eq!(strict, deck);
+            assert_eq!(lenient, deck);
+        }
+    }
+
+    #[test]
+    fn test_lenient_stray_numbers() {
+        let deck: Deck = Deck::from_passphrase("stray");
+        for label in &["Deck 1:", "Deck 60:"] {
+            let text = format!("{} {}", label, deck);
+            let parsed: Deck = MaybeDeck::parse_lenient(&text).unwrap().check().unwrap();
+            assert_eq!(parsed, deck);
+        }
+        let numbers = format!("Deck: {}", deck.format(CardFormat::Number));
+        let parsed: Deck = MaybeDeck::parse_lenient(&numbers).unwrap().check().unwrap();
+        assert_eq!(parsed, deck);
+    }
+
+    #[test]
+    fn test_joker_names() {
+        let jokers: JokerNames = "Big=B,Little=A".parse().unwrap();
+        let text = "Little \u{1F0A1} Th Big";
+        for parsed in &[
+            MaybeDeck::parse_strict_with(text, &jokers).unwrap(),
+            MaybeDeck::parse_lenient_with(text, &jokers).unwrap(),
+        ] {
+            assert_eq!(parsed.0, [53, 40, 36, 54]);
+        }
+        assert!(MaybeDeck::parse_strict(text).is_err());
+    }
+
+    #[test]
+    fn test_formats_roundtrip() {
+        let deck: Deck = Deck::from_passphrase("formats");
+        for &format in &[
+            CardFormat::Symbols,
+            CardFormat::Ascii,
+            CardFormat::Glyph,
+            CardFormat::Name,
+            CardFormat::Number,
+            CardFormat::Ansi,
+        ] {
+            let text = deck.format(format);
+            let strict: Deck = MaybeDeck::parse_strict(&text).unwrap().check().unwrap();
+            let lenient: Deck = MaybeDeck::parse_lenient(&text).unwrap().check().unwrap();
+            assert_eq!(strict, deck, "{:?}", format);
+            assert_eq!(lenient, deck, "{:?}", format);
+        }
+        let names = deck.format(CardFormat::Name).replace(',', "");
+        let strict: Deck = MaybeDeck::parse_strict(&names).unwrap().check().unwrap();
+        let lenient: Deck = MaybeDeck::parse_lenient(&names).unwrap().check().unwrap();
+        assert_eq!(strict, deck);
+        assert_eq!(lenient, deck);
+    }
+
+    #[test]
+    fn test_ansi_spans() {
+        let err = MaybeDeck::parse_strict("\x1b[31mA♡\x1b[0m X♡").unwrap_err();
+        match err {
+            DeckError::Unrecognized(errors) => assert_eq!(errors[0].span, 14..18),
+            _ => panic!("expected unrecognized tokens; got {:?}", err),
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! Models of the ways people physically shuffle a deck of cards.
+//!
+//! [`Deck::shuffle_with`] produces a uniformly random deck in a single step,
+//! which is ideal for generating keys. Real shuffles are much weaker: these
+//! models show how many of them a real deck needs before it is well mixed.
+//!
+//! The usual measure of mixing is the number of rising sequences: maximal
+//! runs of consecutive cards, such as `3, 4, 5`, which appear in increasing
+//! order even if other cards lie between them. A sorted deck has one rising
+//! sequence, and each riffle shuffle can at most double the count. A
+//! uniformly random deck of `n` cards has `(n + 1) / 2` on average.
+
+use super::Deck;
+use rand::{CryptoRng, Rng};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// how likely an overhand shuffle is to break the deck between any two cards
+///
+/// Packets therefore average about five cards.
+const OVERHAND_BREAK_PROBABILITY: f64 = 0.2;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("unknown shuffle method {0:?}; expected one of `uniform`, `riffle`, `overhand`, or `cut`")]
+pub struct UnknownMethod(String);
+
+/// A way of shuffling a deck.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShuffleMethod {
+    /// a perfect shuffle, producing every ordering with equal probability
+    #[default]
+    Uniform,
+    /// a riffle shuffle, following the Gilbert–Shannon–Reeds model
+    Riffle,
+    /// an overhand shuffle, following Pemantle's model
+    Overhand,
+    /// a single cut
+    Cut,
+}
+
+impl FromStr for ShuffleMethod {
+    type Err = UnknownMethod;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "uniform" => Ok(ShuffleMethod::Uniform),
+            "riffle" => Ok(ShuffleMethod::Riffle),
+            "overhand" => Ok(ShuffleMethod::Overhand),
+            "cut" => Ok(ShuffleMethod::Cut),
+            _ => Err(UnknownMethod(s.to_string())),
+        }
+    }
+}
+
+/// choose a position near the middle of the deck, as a person cutting it would
+fn binomial_cut<R: Rng>(rng: &mut R, deck_size: usize) -> usize {
+    (0..deck_size).filter(|_| rng.gen::<bool>()).count()
+}
+
+impl<const DECK_SIZE: usize> Deck<DECK_SIZE> {
+    /// Shuffle this deck once, using the given method.
+    pub fn shuffle_by<R: Rng + CryptoRng>(&mut self, method: ShuffleMethod, rng: &mut R) {
+        match method {
+            ShuffleMethod::Uniform => self.shuffle_with(rng),
+            ShuffleMethod::Riffle => self.riffle_with(rng),
+            ShuffleMethod::Overhand => self.overhand_with(rng),
+            ShuffleMethod::Cut => self.cut_with(rng),
+        }
+    }
+
+    /// Riffle shuffle this deck once.
+    ///
+    /// The deck is cut into two packets at a binomially-distributed position,
+    /// then the packets are interleaved, dropping each card from a packet
+    /// with probability proportional to the size of that packet.
+    pub fn riffle_with<R: Rng>(&mut self, rng: &mut R) {
+        let cut = binomial_cut(rng, DECK_SIZE);
+        let (mut left, mut right) = (&self.0[..cut], &self.0[cut..]);
+        let mut next = [0; DECK_SIZE];
+        for card in next.iter_mut() {
+            let from_left = rng.gen_range(0..left.len() + right.len()) < left.len();
+            let packet = if from_left { &mut left } else { &mut right };
+            *card = packet[0];
+            *packet = &packet[1..];
+        }
+        self.0 = next;
+    }
+
+    /// Overhand shuffle this deck once.
+    ///
+    /// The deck is broken into packets of random sizes, which are moved from
+    /// the top of the deck to a new pile one at a time, so that their order
+    /// is reversed while the order of the cards within each one is kept.
+    pub fn overhand_with<R: Rng>(&mut self, rng: &mut R) {
+        let mut next = [0; DECK_SIZE];
+        let mut end = DECK_SIZE;
+        let mut start = 0;
+        while start < DECK_SIZE {
+            let mut len = 1;
+            while start + len < DECK_SIZE && !rng.gen_bool(OVERHAND_BREAK_PROBABILITY) {
+                len += 1;
+            }
+            next[end - len..end].copy_from_slice(&self.0[start..start + len]);
+            end -= len;
+            start += len;
+        }
+        self.0 = next;
+    }
+
+    /// Cut this deck once, near the middle, moving the top packet to the bottom.
+    pub fn cut_with<R: Rng>(&mut self, rng: &mut R) {
+        let cut = binomial_cut(rng, DECK_SIZE);
+        self.0.rotate_left(cut);
+    }
+
+    /// the number of rising sequences in this deck
+    ///
+    /// A sorted deck has one; a uniformly random deck has `(DECK_SIZE + 1) / 2`
+    /// on average.
+    pub fn rising_sequences(&self) -> usize {
+        let mut positions = [0; DECK_SIZE];
+        for (idx, &card) in self.0.iter().enumerate() {
+            positions[card as usize - 1] = idx;
+        }
+        1 + positions
+            .windows(2)
+            .filter(|pair| pair[1] < pair[0])
+            .count()
+    }
+}
+
+/// the mean number of rising sequences in a uniformly random deck of `deck_size` cards
+///
+/// Each of the `n - 1` adjacent pairs of values is out of order with
+/// probability one half, and each such pair begins a new sequence.
+pub fn expected_rising_sequences(deck_size: usize) -> f64 {
+    (deck_size as f64 + 1.0) / 2.0
+}
+
+/// the total variation distance from uniform of a deck after `riffles` riffle shuffles
+///
+/// This is the formula of Bayer and Diaconis: after `k` riffles, an ordering
+/// with `r` rising sequences has probability `C(2^k + n - r, n) / 2^(k n)`.
+/// A distance of 1 means that the deck is entirely predictable, and 0 means
+/// that it is perfectly mixed. A 52-card deck needs about seven riffles
+/// before the distance falls below one half.
+pub fn riffle_variation_distance(deck_size: usize, riffles: u32) -> f64 {
+    let n = deck_size;
+    // 2^riffles overflows for many riffles, so probabilities are computed
+    // relative to it, in log space
+    let ln_shuffles = riffles as f64 * std::f64::consts::LN_2;
+    let shuffles = ln_shuffles.exp();
+    let ln_factorial: f64 = (1..=n).map(|i| (i as f64).ln()).sum();
+    let uniform = (-ln_factorial).exp();
+
+    // eulerian[d]: the number of orderings with d descents, i.e. d + 1 rising sequences
+    let mut eulerian = vec![0.0; n.max(1)];
+    eulerian[0] = 1.0;
+    for size in 2..=n {
+        for d in (1..size).rev() {
+            eulerian[d] = (size - d) as f64 * eulerian[d - 1] + (d + 1) as f64 * eulerian[d];
+        }
+    }
+
+    let distance: f64 = (1..=n)
+        .map(|r| {
+            let top = shuffles + (n - r) as f64;
+            let probability = if top < n as f64 {
+                0.0
+            } else {
+                // ln(C(2^k + n - r, n) / 2^(k n)), one factor of 2^k per term
+                let ln_probability: f64 = (1..=n)
+                    .map(|i| ((i as f64 - r as f64) / shuffles).ln_1p() - (i as f64).ln())
+                    .sum();
+                ln_probability.exp()
+            };
+            eulerian[r - 1] * (probability - uniform).abs()
+        })
+        .sum();
+    distance / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn is_permutation(deck: &Deck) -> bool {
+        let mut cards = deck.0;
+        cards.sort_unstable();
+        cards == Deck::<54>::new().0
+    }
+
+    #[test]
+    fn test_models_permute() {
+        let mut rng = StdRng::seed_from_u64(15);
+        for method in &[
+            ShuffleMethod::Uniform,
+            ShuffleMethod::Riffle,
+            ShuffleMethod::Overhand,
+            ShuffleMethod::Cut,
+        ] {
+            let mut deck = Deck::new();
+            for _ in 0..10 {
+                deck.shuffle_by(*method, &mut rng);
+                assert!(is_permutation(&deck), "{:?} lost a card", method);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rising_sequences() {
+        let mut rng = StdRng::seed_from_u64(15);
+        let mut deck = Deck::<54>::new();
+        assert_eq!(deck.rising_sequences(), 1);
+        deck.riffle_with(&mut rng);
+        assert!(deck.rising_sequences() <= 2);
+        deck.riffle_with(&mut rng);
+        assert!(deck.rising_sequences() <= 4);
+
+        let mut deck = Deck::<54>::new();
+        deck.cut_with(&mut rng);
+        assert!(deck.rising_sequences() <= 2);
+    }
+
+    #[test]
+    fn test_riffle_variation_distance() {
+        // Bayer and Diaconis' table for a 52-card deck
+        let expected = [
+            1.000, 1.000, 1.000, 1.000, 0.924, 0.614, 0.334, 0.167, 0.085,
+        ];
+        for (riffles, &distance) in expected.iter().enumerate() {
+            let riffles = riffles as u32 + 1;
+            let actual = riffle_variation_distance(52, riffles);
+            assert!(
+                (actual - distance).abs() < 0.001,
+                "after {} riffles: expected {}, got {}",
+                riffles,
+                distance,
+                actual
+            );
+        }
+        for &riffles in &[64, 1100, u32::MAX] {
+            let actual = riffle_variation_distance(52, riffles);
+            assert!(
+                actual.abs() < 1e-9,
+                "after {} riffles: got {}",
+                riffles,
+                actual
+            );
+        }
+    }
+
+    #[test]
+    fn test_expected_rising_sequences() {
+        assert_eq!(expected_rising_sequences(54), 27.5);
+        assert_eq!(expected_rising_sequences(8), 4.5);
+    }
+}
@@ -0,0 +1,227 @@
+//! Explaining what is wrong with a list of cards which should form a deck.
+
+use crate::card::Card;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// the name of a card in a deck of `deck_size` cards
+pub(crate) fn card_name(value: u8, deck_size: usize) -> String {
+    if value as usize == deck_size - 1 {
+        return "AJ".to_string();
+    }
+    if value as usize == deck_size {
+        return "BJ".to_string();
+    }
+    match Card::try_from(value) {
+        Ok(card) => card.to_string(),
+        Err(_) => value.to_string(),
+    }
+}
+
+/// join a list of items in English: `a`, `a and b`, `a, b, and c`
+fn join<T: fmt::Display>(items: &[T]) -> String {
+    match items {
+        [] => String::new(),
+        [only] => only.to_string(),
+        [first, second] => format!("{} and {}", first, second),
+        [rest @ .., last] => {
+            let rest: Vec<_> = rest.iter().map(ToString::to_string).collect();
+            format!("{}, and {}", rest.join(", "), last)
+        }
+    }
+}
+
+/// Everything wrong with a list of cards which should form a deck.
+///
+/// Positions count from 1, at the top of the deck.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeckProblems {
+    /// how many cards a deck needs
+    pub expected: usize,
+    /// how many cards were found
+    pub actual: usize,
+    /// each card which appears more than once, with every position at which it appears
+    pub duplicates: Vec<(u8, Vec<usize>)>,
+    /// the cards which do not appear at all
+    pub missing: Vec<u8>,
+    /// the position and value of each card which is not in the deck's range
+    pub out_of_bounds: Vec<(usize, u8)>,
+}
+
+impl DeckProblems {
+    /// Examine a list of card values which should form a deck of `deck_size` cards.
+    pub fn examine(cards: &[u8], deck_size: usize) -> DeckProblems {
+        let mut positions = vec![Vec::new(); deck_size];
+        let mut out_of_bounds = Vec::new();
+        for (idx, &card) in cards.iter().enumerate() {
+            if card == 0 || card as usize > deck_size {
+                out_of_bounds.push((idx + 1, card));
+            } else {
+                positions[card as usize - 1].push(idx + 1);
+            }
+        }
+        let mut duplicates = Vec::new();
+        let mut missing = Vec::new();
+        for (idx, positions) in positions.into_iter().enumerate() {
+            let card = idx as u8 + 1;
+            match positions.len() {
+                0 => missing.push(card),
+                1 => {}
+                _ => duplicates.push((card, positions)),
+            }
+        }
+        DeckProblems {
+            expected: deck_size,
+            actual: cards.len(),
+            duplicates,
+            missing,
+            out_of_bounds,
+        }
+    }
+
+    /// whether the cards form a valid deck
+    pub fn is_empty(&self) -> bool {
+        self.actual == self.expected
+            && self.duplicates.is_empty()
+            && self.missing.is_empty()
+            && self.out_of_bounds.is_empty()
+    }
+
+    fn name(&self, card: u8) -> String {
+        card_name(card, self.expected)
+    }
+
+    /// A fix, if exactly one card is wrong.
+    pub fn suggestion(&self) -> Option<String> {
+        let missing = match self.missing[..] {
+            [missing] => Some(self.name(missing)),
+            [] => None,
+            _ => return None,
+        };
+        let extra = self.actual as isize - self.expected as isize;
+        match (
+            &self.duplicates[..],
+            &self.out_of_bounds[..],
+            missing,
+            extra,
+        ) {
+            ([(card, positions)], [], Some(missing), 0) if positions.len() == 2 => Some(format!(
+                "replace the {} at position {} or {} with {}",
+                self.name(*card),
+                positions[0],
+                positions[1],
+                missing
+            )),
+            ([(card, positions)], [], None, 1) if positions.len() == 2 => Some(format!(
+                "remove the {} at position {} or {}",
+                self.name(*card),
+                positions[0],
+                positions[1]
+            )),
+            ([], [(position, value)], Some(missing), 0) => Some(format!(
+                "replace the {} at position {} with {}",
+                value, position, missing
+            )),
+            ([], [], Some(missing), -1) => Some(format!("insert the missing {}", missing)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DeckProblems {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.actual != self.expected {
+            write!(
+                f,
+                "wrong number of cards in deck: need {}, got {}",
+                self.expected, self.actual
+            )?;
+        } else if !self.out_of_bounds.is_empty() {
+            write!(f, "cards in a deck must range from 1..={}", self.expected)?;
+        } else {
+            write!(f, "each card in a deck must be unique")?;
+        }
+        for (position, value) in &self.out_of_bounds {
+            write!(f, "; position {} reads {}", position, value)?;
+        }
+        for (card, positions) in &self.duplicates {
+            write!(
+                f,
+                "; {} appears at positions {}",
+                self.name(*card),
+                join(positions)
+            )?;
+        }
+        if !self.missing.is_empty() {
+            let missing: Vec<_> = self.missing.iter().map(|&card| self.name(card)).collect();
+            write!(f, "; missing {}", join(&missing))?;
+        }
+        if let Some(suggestion) = self.suggestion() {
+            write!(f, "; suggestion: {}", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted() -> Vec<u8> {
+        (1..=54).collect()
+    }
+
+    #[test]
+    fn test_valid() {
+        assert!(DeckProblems::examine(&sorted(), 54).is_empty());
+    }
+
+    #[test]
+    fn test_one_duplicate() {
+        let mut cards = sorted();
+        // 7♦ is mistyped as 7♥
+        cards[19] = 33;
+        let problems = DeckProblems::examine(&cards, 54);
+        assert_eq!(problems.duplicates, vec![(33, vec![20, 33])]);
+        assert_eq!(problems.missing, vec![20]);
+        assert_eq!(
+            problems.to_string(),
+            "each card in a deck must be unique; 7♡ appears at positions 20 and 33; \
+             missing 7♢; suggestion: replace the 7♡ at position 20 or 33 with 7♢"
+        );
+    }
+
+    #[test]
+    fn test_wrong_number() {
+        let mut cards = sorted();
+        cards.remove(53);
+        let problems = DeckProblems::examine(&cards, 54);
+        assert_eq!(problems.suggestion().unwrap(), "insert the missing BJ");
+
+        let mut cards = sorted();
+        cards.push(1);
+        let problems = DeckProblems::examine(&cards, 54);
+        assert_eq!(
+            problems.suggestion().unwrap(),
+            "remove the 1♧ at position 1 or 55"
+        );
+    }
+
+    #[test]
+    fn test_several_problems() {
+        let mut cards = sorted();
+        cards[0] = 2;
+        cards[1] = 0;
+        cards[2] = 4;
+        let problems = DeckProblems::examine(&cards, 54);
+        assert_eq!(problems.out_of_bounds, vec![(2, 0)]);
+        assert_eq!(problems.duplicates, vec![(4, vec![3, 4])]);
+        assert_eq!(problems.missing, vec![1, 3]);
+        assert_eq!(problems.suggestion(), None);
+    }
+
+    #[test]
+    fn test_join() {
+        assert_eq!(join(&[1, 2, 3]), "1, 2, and 3");
+    }
+}
@@ -0,0 +1,556 @@
+//! Finding where a hand computation went wrong.
+//!
+//! A [`Replay`] is the sequence of steps a person should follow: keying a
+//! sorted deck with a passphrase, if any, then generating keystream. Steps
+//! are numbered from 1, just as in a [`Trace`](crate::trace::Trace).
+//!
+//! Given the deck the person ended up with, or the keystream letters they
+//! produced, the replay finds the first step at which their work cannot be
+//! correct, and which single common mistake, made at which step, would
+//! explain it.
+
+use crate::card::Joker;
+use crate::deck::{Deck, KeyingOptions, DEFAULT_DECK_SIZE};
+use crate::textbyte::textbyte;
+use crate::trace::Step;
+use std::fmt;
+
+/// how many keystream rounds are searched for a deck, by default
+pub const DEFAULT_ROUNDS: usize = 10;
+
+/// A common mistake in following a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mistake {
+    /// the step was left out
+    Skipped,
+    /// a joker was moved up the deck instead of down
+    WrongWay,
+    /// a joker was moved the wrong number of cards
+    WrongDistance { expected: usize, moved: usize },
+    /// a cut was made below the wrong card
+    WrongCut { expected: usize, counted: usize },
+    /// the output card was found by counting the wrong number of cards
+    WrongOutputCount { expected: usize, counted: usize },
+}
+
+impl fmt::Display for Mistake {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mistake::Skipped => write!(f, "the step was skipped"),
+            Mistake::WrongWay => write!(f, "the joker was moved up instead of down"),
+            Mistake::WrongDistance { expected, moved } => {
+                write!(f, "the joker was moved {} instead of {}", moved, expected)
+            }
+            Mistake::WrongCut { expected, counted } => write!(
+                f,
+                "the cut was made below card {} instead of card {}",
+                counted, expected
+            ),
+            Mistake::WrongOutputCount { expected, counted } => write!(
+                f,
+                "{} cards were counted to find the output instead of {}",
+                counted, expected
+            ),
+        }
+    }
+}
+
+/// A single mistake which would explain a person's work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Suspect {
+    /// the number of the step, counting from 1
+    pub step: usize,
+    /// what the step should have done
+    pub expected: Step,
+    /// what went wrong
+    pub mistake: Mistake,
+}
+
+impl fmt::Display for Suspect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "step {} ({}): {}",
+            self.step, self.expected, self.mistake
+        )
+    }
+}
+
+/// How a person's work compares to the algorithm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Diagnosis {
+    /// the work is correct, as of this many steps
+    Correct { steps: usize },
+    /// the work went wrong
+    Diverged {
+        /// the first step whose result cannot match the work
+        ///
+        /// If no single mistake explains a deck, this is the step after the
+        /// correct deck which most resembles it.
+        step: usize,
+        /// single mistakes which would explain the work, earliest first
+        suspects: Vec<Suspect>,
+    },
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnosis::Correct { steps } => write!(f, "correct after {} steps", steps),
+            Diagnosis::Diverged { step, suspects } => {
+                write!(f, "went wrong by step {}", step)?;
+                if suspects.is_empty() {
+                    write!(f, "; no single mistake explains it")?;
+                }
+                for suspect in suspects {
+                    write!(f, "\n  likely: {}", suspect)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// the letter, from 1 to 26, of a keystream value
+fn letter(value: u8) -> u8 {
+    (value - 1) % 26 + 1
+}
+
+/// something a person does to the deck, or reads from it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    /// a step which moves cards
+    Move(Step),
+    /// reading an output card, which may be a joker
+    Read,
+}
+
+/// the actions of a keystream round
+const ROUND: [Action; 5] = [
+    Action::Move(Step::Push {
+        joker: Joker::A,
+        by: 1,
+    }),
+    Action::Move(Step::Push {
+        joker: Joker::B,
+        by: 2,
+    }),
+    Action::Move(Step::TripleCut),
+    Action::Move(Step::CountCut),
+    Action::Read,
+];
+
+/// The steps a person should follow, from a known starting deck.
+#[derive(Debug, Clone)]
+pub struct Replay<const DECK_SIZE: usize = DEFAULT_DECK_SIZE> {
+    start: Deck<DECK_SIZE>,
+    /// the steps of keying, before the keystream begins
+    keying: Vec<Step>,
+}
+
+impl<const DECK_SIZE: usize> Replay<DECK_SIZE> {
+    /// Generate keystream from a deck.
+    pub fn from_deck(deck: Deck<DECK_SIZE>) -> Replay<DECK_SIZE> {
+        Replay {
+            start: deck,
+            keying: Vec::new(),
+        }
+    }
+
+    /// Key a sorted deck with a passphrase, then generate keystream.
+    pub fn from_passphrase(phrase: &str, options: KeyingOptions) -> Replay<DECK_SIZE> {
+        let mut keying = Vec::new();
+        Deck::<DECK_SIZE>::from_passphrase_observed(
+            phrase,
+            options,
+            &mut |step, _: &Deck<DECK_SIZE>| keying.push(step),
+        );
+        Replay {
+            start: Deck::new(),
+            keying,
+        }
+    }
+
+    /// the action at index `idx`, counting from 0
+    fn action(&self, idx: usize) -> Action {
+        match self.keying.get(idx) {
+            Some(&step) => Action::Move(step),
+            None => ROUND[(idx - self.keying.len()) % ROUND.len()],
+        }
+    }
+
+    /// the value of a card when counting
+    fn count(card: u8) -> usize {
+        (card as usize).min(DECK_SIZE - 1)
+    }
+
+    /// cut below card `n`, if that is possible, keeping the bottom card in place
+    fn cut(deck: &mut Deck<DECK_SIZE>, n: usize) -> bool {
+        if n >= DECK_SIZE {
+            return false;
+        }
+        deck.count_cut(Some(n as u8));
+        true
+    }
+
+    /// perform a step correctly
+    fn apply(deck: &mut Deck<DECK_SIZE>, step: Step) {
+        match step {
+            Step::Push { joker, by } => deck.push(Deck::<DECK_SIZE>::joker(joker), by),
+            Step::TripleCut => {
+                deck.triple_cut(Deck::<DECK_SIZE>::JOKER_A, Deck::<DECK_SIZE>::JOKER_B)
+            }
+            Step::CountCut => deck.count_cut(None),
+            Step::LetterCut { letter } => {
+                deck.count_cut(Some((letter as usize % (DECK_SIZE - 1)) as u8))
+            }
+            Step::PlaceJoker { joker, n } => deck.place(Deck::<DECK_SIZE>::joker(joker), n),
+            Step::Output { .. } | Step::SkipJoker { .. } => {}
+        }
+    }
+
+    /// every way of getting a step wrong, with the deck each produces
+    fn mistakes(before: &Deck<DECK_SIZE>, step: Step) -> Vec<(Mistake, Deck<DECK_SIZE>)> {
+        let mut out = vec![(Mistake::Skipped, before.clone())];
+        match step {
+            Step::Push { joker, by } => {
+                let card = Deck::<DECK_SIZE>::joker(joker);
+                let mut deck = before.clone();
+                deck.unpush(card, by, false);
+                out.push((Mistake::WrongWay, deck));
+                for moved in [by - 1, by + 1] {
+                    if moved > 0 {
+                        let mut deck = before.clone();
+                        deck.push(card, moved);
+                        out.push((
+                            Mistake::WrongDistance {
+                                expected: by,
+                                moved,
+                            },
+                            deck,
+                        ));
+                    }
+                }
+            }
+            Step::CountCut | Step::LetterCut { .. } => {
+                let expected = match step {
+                    Step::LetterCut { letter } => letter as usize % (DECK_SIZE - 1),
+                    _ => Self::count(before.values()[DECK_SIZE - 1]),
+                };
+                for counted in [expected.wrapping_sub(1), expected + 1] {
+                    let mut deck = before.clone();
+                    if Self::cut(&mut deck, counted) {
+                        out.push((Mistake::WrongCut { expected, counted }, deck));
+                    }
+                }
+            }
+            _ => {}
+        }
+        out
+    }
+
+    /// the correct deck after each step, up to `steps` steps, starting with the initial deck
+    fn decks(&self, steps: usize) -> Vec<Deck<DECK_SIZE>> {
+        let mut deck = self.start.clone();
+        let mut out = vec![deck.clone()];
+        for idx in 0..steps {
+            if let Action::Move(step) = self.action(idx) {
+                Self::apply(&mut deck, step);
+            }
+            out.push(deck.clone());
+        }
+        out
+    }
+
+    /// Compare the deck a person holds to the correct deck after each step.
+    ///
+    /// All of keying and `rounds` rounds of keystream generation are searched.
+    pub fn diagnose_deck(&self, current: &Deck<DECK_SIZE>, rounds: usize) -> Diagnosis {
+        let steps = self.keying.len() + rounds * ROUND.len();
+        let decks = self.decks(steps);
+        if let Some(matched) = decks.iter().rposition(|deck| deck == current) {
+            return Diagnosis::Correct { steps: matched };
+        }
+
+        let mut suspects = Vec::new();
+        for (idx, before) in decks.iter().enumerate().take(steps) {
+            let step = match self.action(idx) {
+                Action::Move(step) => step,
+                Action::Read => continue,
+            };
+            for (mistake, mut deck) in Self::mistakes(before, step) {
+                let mut later = idx + 1;
+                loop {
+                    if deck == *current {
+                        suspects.push(Suspect {
+                            step: idx + 1,
+                            expected: step,
+                            mistake,
+                        });
+                        break;
+                    }
+                    if later >= steps {
+                        break;
+                    }
+                    if let Action::Move(step) = self.action(later) {
+                        Self::apply(&mut deck, step);
+                    }
+                    later += 1;
+                }
+            }
+        }
+
+        let step = match suspects.first() {
+            Some(suspect) => suspect.step,
+            None => {
+                let in_place = |deck: &Deck<DECK_SIZE>| {
+                    deck.values()
+                        .iter()
+                        .zip(current.values())
+                        .filter(|(a, b)| a == b)
+                        .count()
+                };
+                let closest = (0..decks.len())
+                    .max_by_key(|&idx| (in_place(&decks[idx]), std::cmp::Reverse(idx)))
+                    .expect("there is always an initial deck");
+                // the step after the closest deck, within those searched
+                (closest + 1).min(steps)
+            }
+        };
+        Diagnosis::Diverged { step, suspects }
+    }
+
+    /// Run the algorithm from step index `from` with `deck`, collecting the
+    /// letters output until there are `count` of them.
+    ///
+    /// Stops early if that takes unreasonably many steps.
+    fn outputs(&self, mut from: usize, mut deck: Deck<DECK_SIZE>, count: usize) -> Vec<u8> {
+        let mut out = Vec::with_capacity(count);
+        // a joker output costs a round, and two in a row are impossible
+        let limit = from + self.keying.len() + 3 * ROUND.len() * (count + 1);
+        while out.len() < count && from < limit {
+            match self.action(from) {
+                Action::Move(step) => Self::apply(&mut deck, step),
+                Action::Read => {
+                    if let Some(value) = deck.output() {
+                        out.push(letter(value));
+                    }
+                }
+            }
+            from += 1;
+        }
+        out
+    }
+
+    /// Compare the keystream letters a person produced to the correct ones.
+    ///
+    /// Characters other than letters are ignored.
+    pub fn diagnose_letters(&self, letters: &str) -> Diagnosis {
+        let letters: Vec<u8> = textbyte(letters).collect();
+        let expected = self.outputs(0, self.start.clone(), letters.len());
+        let wrong = match letters.iter().zip(&expected).position(|(a, b)| a != b) {
+            Some(wrong) => wrong,
+            None => {
+                let mut steps = 0;
+                let mut reads = 0;
+                let mut deck = self.start.clone();
+                while reads < letters.len() {
+                    match self.action(steps) {
+                        Action::Move(step) => Self::apply(&mut deck, step),
+                        Action::Read => reads += deck.output().is_some() as usize,
+                    }
+                    steps += 1;
+                }
+                return Diagnosis::Correct { steps };
+            }
+        };
+
+        // walk the correct computation up to the read of the first wrong letter
+        let mut deck = self.start.clone();
+        let mut reads = 0;
+        let mut suspects = Vec::new();
+        let mut idx = 0;
+        loop {
+            match self.action(idx) {
+                Action::Move(step) => {
+                    for (mistake, mistaken) in Self::mistakes(&deck, step) {
+                        let rest = self.outputs(idx + 1, mistaken, letters.len() - reads);
+                        if rest == letters[reads..] {
+                            suspects.push(Suspect {
+                                step: idx + 1,
+                                expected: step,
+                                mistake,
+                            });
+                        }
+                    }
+                    Self::apply(&mut deck, step);
+                }
+                Action::Read => match deck.output() {
+                    Some(value) if reads == wrong => {
+                        let top = Self::count(deck.values()[0]);
+                        let later = self.outputs(idx + 1, deck.clone(), letters.len() - wrong - 1);
+                        for counted in [top - 1, top + 1] {
+                            if counted >= DECK_SIZE || later != letters[wrong + 1..] {
+                                continue;
+                            }
+                            let read = deck.values()[counted];
+                            if read as usize <= DECK_SIZE - 2 && letter(read) == letters[wrong] {
+                                suspects.push(Suspect {
+                                    step: idx + 1,
+                                    expected: Step::Output { value },
+                                    mistake: Mistake::WrongOutputCount {
+                                        expected: top,
+                                        counted,
+                                    },
+                                });
+                            }
+                        }
+                        return Diagnosis::Diverged {
+                            step: idx + 1,
+                            suspects,
+                        };
+                    }
+                    Some(_) => reads += 1,
+                    None => {}
+                },
+            }
+            idx += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::textbyte::value_letter;
+    use crate::Keystream;
+
+    fn keystream_letters(deck: Deck, count: usize) -> String {
+        Keystream::from(deck)
+            .take(count)
+            .map(value_letter)
+            .collect()
+    }
+
+    #[test]
+    fn test_correct() {
+        let replay = Replay::<DEFAULT_DECK_SIZE>::from_passphrase("key", KeyingOptions::default());
+        let deck = Deck::from_passphrase("key");
+        assert_eq!(
+            replay.diagnose_deck(&deck, 0),
+            Diagnosis::Correct { steps: 15 }
+        );
+        assert!(matches!(
+            replay.diagnose_letters(&keystream_letters(deck, 5)),
+            Diagnosis::Correct { .. }
+        ));
+    }
+
+    #[test]
+    fn test_off_by_one_count_cut() {
+        let start: Deck = Deck::from_passphrase("diagnose");
+        let replay = Replay::from_deck(start.clone());
+
+        // the first round, with a count cut one card too deep
+        let mut deck = start;
+        deck.push(Deck::<DEFAULT_DECK_SIZE>::JOKER_A, 1);
+        deck.push(Deck::<DEFAULT_DECK_SIZE>::JOKER_B, 2);
+        deck.triple_cut(
+            Deck::<DEFAULT_DECK_SIZE>::JOKER_A,
+            Deck::<DEFAULT_DECK_SIZE>::JOKER_B,
+        );
+        let bottom = Replay::<DEFAULT_DECK_SIZE>::count(deck.values()[DEFAULT_DECK_SIZE - 1]);
+        deck.count_cut(Some(bottom as u8 + 1));
+
+        let diagnosis = replay.diagnose_deck(&deck, DEFAULT_ROUNDS);
+        let expected = Suspect {
+            step: 4,
+            expected: Step::CountCut,
+            mistake: Mistake::WrongCut {
+                expected: bottom,
+                counted: bottom + 1,
+            },
+        };
+        match diagnosis {
+            Diagnosis::Diverged { step, suspects } => {
+                assert_eq!(step, 4);
+                assert!(suspects.contains(&expected), "{:?}", suspects);
+            }
+            _ => panic!("expected a mistake; got {:?}", diagnosis),
+        }
+    }
+
+    #[test]
+    fn test_joker_moved_up_to_top() {
+        // joker A is 2nd, and is moved up to the top instead of down to 3rd
+        let mut start: Deck = Deck::new();
+        start.place(Deck::<DEFAULT_DECK_SIZE>::JOKER_A, 1);
+        let replay = Replay::from_deck(start.clone());
+        let mut deck = start;
+        deck.unpush(Deck::<DEFAULT_DECK_SIZE>::JOKER_A, 1, false);
+        assert_eq!(deck.values()[0], Deck::<DEFAULT_DECK_SIZE>::JOKER_A);
+
+        match replay.diagnose_deck(&deck, 1) {
+            Diagnosis::Diverged { step, suspects } => {
+                assert_eq!(step, 1);
+                assert!(suspects.contains(&Suspect {
+                    step: 1,
+                    expected: Step::Push {
+                        joker: Joker::A,
+                        by: 1
+                    },
+                    mistake: Mistake::WrongWay,
+                }));
+            }
+            diagnosis => panic!("expected a mistake; got {:?}", diagnosis),
+        }
+    }
+
+    #[test]
+    fn test_closest_step_is_searched() {
+        let replay = Replay::from_deck(Deck::<DEFAULT_DECK_SIZE>::new());
+        let unrelated = Deck::from_passphrase("unrelated");
+        for rounds in 0..3 {
+            match replay.diagnose_deck(&unrelated, rounds) {
+                Diagnosis::Diverged { step, .. } => assert!(step <= rounds * ROUND.len()),
+                diagnosis => panic!("expected a mistake; got {:?}", diagnosis),
+            }
+        }
+    }
+
+    #[test]
+    fn test_joker_moved_up() {
+        let start: Deck = Deck::from_passphrase("letters");
+        let replay = Replay::from_deck(start.clone());
+        let correct = keystream_letters(start.clone(), 10);
+
+        // joker A moved up rather than down in the fourth round
+        let mut keystream = Keystream::from(start);
+        let mut letters: String = keystream.by_ref().take(3).map(value_letter).collect();
+        let mut deck = keystream.into_deck();
+        deck.push(Deck::<DEFAULT_DECK_SIZE>::JOKER_A, DEFAULT_DECK_SIZE - 2);
+        deck.push(Deck::<DEFAULT_DECK_SIZE>::JOKER_B, 2);
+        deck.triple_cut(
+            Deck::<DEFAULT_DECK_SIZE>::JOKER_A,
+            Deck::<DEFAULT_DECK_SIZE>::JOKER_B,
+        );
+        deck.count_cut(None);
+        let mut keystream = Keystream::from(deck.clone());
+        letters.extend(
+            deck.output()
+                .into_iter()
+                .chain(keystream.by_ref().take(6))
+                .map(value_letter),
+        );
+        assert_ne!(letters, correct);
+
+        match replay.diagnose_letters(&letters) {
+            Diagnosis::Diverged { suspects, .. } => {
+                assert!(suspects
+                    .iter()
+                    .any(|suspect| suspect.mistake == Mistake::WrongWay));
+            }
+            diagnosis => panic!("expected a mistake; got {:?}", diagnosis),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! Short digests of decks, for confirming that two parties hold the same key.
+//!
+//! A [`Fingerprint`] can be compared as hex, as pronounceable
+//! [proquint](https://arxiv.org/html/0901.4016) words, or as groups of letters
+//! read aloud with the NATO phonetic alphabet.
+
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+const CONSONANTS: &[u8; 16] = b"bdfghjklmnprstvz";
+const VOWELS: &[u8; 4] = b"aiou";
+
+const NATO: [&str; 26] = [
+    "Alfa", "Bravo", "Charlie", "Delta", "Echo", "Foxtrot", "Golf", "Hotel", "India", "Juliett",
+    "Kilo", "Lima", "Mike", "November", "Oscar", "Papa", "Quebec", "Romeo", "Sierra", "Tango",
+    "Uniform", "Victor", "Whiskey", "X-ray", "Yankee", "Zulu",
+];
+
+/// how many letters are used to render a fingerprint as letter groups
+const LETTERS: usize = 12;
+/// how many letters are in each group
+const LETTER_GROUP: usize = 4;
+
+/// A short, stable digest of the order of a deck.
+///
+/// This is the 64-bit FNV-1a hash of the card values, from top to bottom.
+/// Decks which differ in any way have, in all likelihood, different
+/// fingerprints.
+///
+/// FNV-1a is fast and not cryptographic, so a fingerprint must be treated as
+/// part of the key. Anyone who learns the fingerprint of a deck keyed from a
+/// passphrase can test guessed passphrases against it offline, cheaply, and
+/// know when they have found the key. Compare fingerprints only over a
+/// channel which would be safe for the key itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Fingerprint(u64);
+
+impl Fingerprint {
+    /// compute the fingerprint of a sequence of card values
+    pub(crate) fn of(values: &[u8]) -> Fingerprint {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
+        Fingerprint(values.iter().fold(OFFSET_BASIS, |hash, &value| {
+            (hash ^ value as u64).wrapping_mul(PRIME)
+        }))
+    }
+
+    /// the digest as an integer
+    pub fn value(self) -> u64 {
+        self.0
+    }
+
+    /// Render the fingerprint as four pronounceable proquint words.
+    ///
+    /// Each word encodes 16 bits as consonant-vowel-consonant-vowel-consonant,
+    /// for example `lusab-babad-gutih-tugad`.
+    pub fn words(self) -> String {
+        (0..4)
+            .rev()
+            .map(|idx| proquint((self.0 >> (16 * idx)) as u16))
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+
+    /// Render the fingerprint as three groups of four letters.
+    ///
+    /// The letters encode about 56 bits of the fingerprint, which is plenty
+    /// to catch a mistake in a deck.
+    pub fn letters(self) -> String {
+        let mut out = String::with_capacity(LETTERS + LETTERS / LETTER_GROUP);
+        for (idx, letter) in self.letter_values().enumerate() {
+            if idx > 0 && idx % LETTER_GROUP == 0 {
+                out.push(' ');
+            }
+            out.push((b'A' + letter) as char);
+        }
+        out
+    }
+
+    /// Render the [letters](Fingerprint::letters) as NATO phonetic code words.
+    ///
+    /// Groups are separated by ` / `.
+    pub fn nato(self) -> String {
+        let words: Vec<_> = self
+            .letter_values()
+            .map(|letter| NATO[letter as usize])
+            .collect();
+        words
+            .chunks(LETTER_GROUP)
+            .map(|group| group.join(" "))
+            .collect::<Vec<_>>()
+            .join(" / ")
+    }
+
+    /// the base-26 digits of the fingerprint, least significant first, as `0..26`
+    fn letter_values(self) -> impl Iterator<Item = u8> {
+        let mut value = self.0;
+        (0..LETTERS).map(move |_| {
+            let letter = (value % 26) as u8;
+            value /= 26;
+            letter
+        })
+    }
+}
+
+/// encode 16 bits as a single proquint word
+fn proquint(bits: u16) -> String {
+    let consonant = |shift: u16| CONSONANTS[((bits >> shift) & 0xf) as usize] as char;
+    let vowel = |shift: u16| VOWELS[((bits >> shift) & 0x3) as usize] as char;
+    [
+        consonant(12),
+        vowel(10),
+        consonant(6),
+        vowel(4),
+        consonant(0),
+    ]
+    .iter()
+    .collect()
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = ParseIntError;
+
+    /// Parse a fingerprint from its hex representation.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u64::from_str_radix(s, 16).map(Fingerprint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Deck;
+
+    #[test]
+    fn test_proquint() {
+        // examples from the proquint specification
+        assert_eq!(proquint(0x7f00), "lusab");
+        assert_eq!(proquint(0x0001), "babad");
+        assert_eq!(
+            Fingerprint(0x7f00_0001_7f00_0001).words(),
+            "lusab-babad-lusab-babad"
+        );
+    }
+
+    #[test]
+    fn test_letters() {
+        assert_eq!(Fingerprint(0).letters(), "AAAA AAAA AAAA");
+        assert_eq!(Fingerprint(27).letters(), "BBAA AAAA AAAA");
+        assert_eq!(
+            Fingerprint(27).nato(),
+            "Bravo Bravo Alfa Alfa / Alfa Alfa Alfa Alfa / Alfa Alfa Alfa Alfa"
+        );
+    }
+
+    #[test]
+    fn test_distinguishes_decks() {
+        let deck: Deck = Deck::from_passphrase("fingerprint");
+        assert_eq!(deck.fingerprint(), deck.clone().fingerprint());
+        assert_ne!(deck.fingerprint(), Deck::<54>::new().fingerprint());
+        assert_eq!(
+            deck.fingerprint().to_string().parse(),
+            Ok(deck.fingerprint())
+        );
+    }
+}
@@ -0,0 +1,372 @@
+//! Plain-language instructions for following the algorithm with a real deck.
+//!
+//! [`Instructions`] is an [`Observer`] which turns each step of keying or of
+//! generating keystream into a physical manipulation, such as "move joker A
+//! (currently 12th) down one, to 13th", together with what the deck should
+//! look like afterwards, so that a beginner can check their work as they go.
+
+use crate::deck::{card_name, Deck, KeyingOptions, DEFAULT_DECK_SIZE};
+use crate::textbyte::value_letter;
+use crate::trace::{Observer, Step};
+use crate::Keystream;
+use std::fmt;
+
+/// how many cards from the top of the deck are shown after a cut
+const SHOWN_CARDS: usize = 3;
+
+/// `1st`, `2nd`, `3rd`, `4th`, and so on
+fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+/// `one card`, `two cards`, `3 cards`, and so on
+fn cards(n: usize) -> String {
+    match n {
+        1 => "one card".to_string(),
+        2 => "two cards".to_string(),
+        _ => format!("{} cards", n),
+    }
+}
+
+/// One physical manipulation of the deck.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    /// the step of the algorithm which this performs
+    pub step: Step,
+    /// what to do
+    pub text: String,
+    /// what should be visible once it is done
+    pub result: String,
+}
+
+/// An observer which writes down each step as an instruction for a person.
+#[derive(Debug, Clone)]
+pub struct Instructions<const DECK_SIZE: usize = DEFAULT_DECK_SIZE> {
+    deck: Deck<DECK_SIZE>,
+    instructions: Vec<Instruction>,
+}
+
+impl<const DECK_SIZE: usize> Instructions<DECK_SIZE> {
+    /// Begin writing instructions for a person holding `deck`.
+    pub fn new(deck: Deck<DECK_SIZE>) -> Instructions<DECK_SIZE> {
+        Instructions {
+            deck,
+            instructions: Vec::new(),
+        }
+    }
+
+    /// Write the instructions for keying a sorted deck with a passphrase.
+    pub fn for_passphrase(phrase: &str, options: KeyingOptions) -> Instructions<DECK_SIZE> {
+        let mut instructions = Instructions::new(Deck::new());
+        Deck::from_passphrase_observed(phrase, options, &mut instructions);
+        instructions
+    }
+
+    /// Write the instructions for generating `count` keystream values from `deck`.
+    pub fn for_keystream(deck: Deck<DECK_SIZE>, count: usize) -> Instructions<DECK_SIZE> {
+        let mut instructions = Instructions::new(deck.clone());
+        let mut keystream = Keystream::from(deck);
+        for _ in 0..count {
+            keystream.next_observed(&mut instructions);
+        }
+        instructions
+    }
+
+    /// every instruction written so far, in order
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    fn name(&self, card: u8) -> String {
+        card_name(card, DECK_SIZE)
+    }
+
+    /// the value of a card when counting: its value, or one less for joker B
+    fn count(card: u8) -> usize {
+        (card as usize).min(DECK_SIZE - 1)
+    }
+
+    /// the 1-based position of a card in a deck
+    fn position(deck: &Deck<DECK_SIZE>, card: u8) -> usize {
+        deck.values()
+            .iter()
+            .position(|&c| c == card)
+            .expect("every card is in the deck")
+            + 1
+    }
+
+    /// describe where a card lies among its neighbours
+    fn surroundings(&self, deck: &Deck<DECK_SIZE>, card: u8) -> String {
+        let values = deck.values();
+        let idx = Self::position(deck, card) - 1;
+        let name = self.name(card);
+        match idx {
+            0 => format!("{} is on top, above {}", name, self.name(values[1])),
+            _ if idx == DECK_SIZE - 1 => {
+                format!(
+                    "{} is on the bottom, below {}",
+                    name,
+                    self.name(values[idx - 1])
+                )
+            }
+            _ => format!(
+                "{} is {}, between {} and {}",
+                name,
+                ordinal(idx + 1),
+                self.name(values[idx - 1]),
+                self.name(values[idx + 1])
+            ),
+        }
+    }
+
+    /// describe the top and bottom of the deck
+    fn ends(&self, deck: &Deck<DECK_SIZE>) -> String {
+        let values = deck.values();
+        let top: Vec<_> = values
+            .iter()
+            .take(SHOWN_CARDS)
+            .map(|&card| self.name(card))
+            .collect();
+        format!(
+            "the deck begins {} … and ends {}",
+            top.join(" "),
+            self.name(values[DECK_SIZE - 1])
+        )
+    }
+
+    /// describe counting down to the output card
+    fn count_down(&self, before: &Deck<DECK_SIZE>) -> String {
+        let top = before.values()[0];
+        let count = Self::count(top);
+        format!(
+            "The top card is {}, worth {}: count {} from the top; the {} card is {}",
+            self.name(top),
+            count,
+            cards(count),
+            ordinal(count + 1),
+            self.name(before.output_card())
+        )
+    }
+
+    fn describe(&self, step: Step, after: &Deck<DECK_SIZE>) -> (String, String) {
+        let before = &self.deck;
+        match step {
+            Step::Push { joker, by } => {
+                let card = Deck::<DECK_SIZE>::joker(joker);
+                let from = Self::position(before, card);
+                let to = Self::position(after, card);
+                let wrap = if from + by > DECK_SIZE {
+                    ", passing the bottom card to continue from the top"
+                } else {
+                    ""
+                };
+                let by = match by {
+                    1 => "one".to_string(),
+                    2 => "two".to_string(),
+                    _ => by.to_string(),
+                };
+                (
+                    format!(
+                        "Move joker {:?} (currently {}) down {}{}, to {}.",
+                        joker,
+                        ordinal(from),
+                        by,
+                        wrap,
+                        ordinal(to)
+                    ),
+                    self.surroundings(after, card),
+                )
+            }
+            Step::TripleCut => {
+                let a = Self::position(before, Deck::<DECK_SIZE>::JOKER_A);
+                let b = Self::position(before, Deck::<DECK_SIZE>::JOKER_B);
+                let (first, second) = (a.min(b), a.max(b));
+                let above = first - 1;
+                let below = DECK_SIZE - second;
+                let text = match (above, below) {
+                    (0, 0) => "Triple cut: the jokers are on the top and bottom, so nothing moves."
+                        .to_string(),
+                    (0, _) => format!(
+                        "Triple cut: move the {} below the lower joker to the top.",
+                        cards(below)
+                    ),
+                    (_, 0) => format!(
+                        "Triple cut: move the {} above the upper joker to the bottom.",
+                        cards(above)
+                    ),
+                    _ => format!(
+                        "Triple cut: swap the {} above the upper joker with the {} below the lower joker.",
+                        cards(above),
+                        cards(below)
+                    ),
+                };
+                (text, self.ends(after))
+            }
+            Step::CountCut => {
+                let bottom = before.values()[DECK_SIZE - 1];
+                let count = Self::count(bottom);
+                (
+                    format!(
+                        "The bottom card is {}, worth {}: cut below the {} card, keeping the bottom card in place.",
+                        self.name(bottom),
+                        count,
+                        ordinal(count)
+                    ),
+                    self.ends(after),
+                )
+            }
+            Step::LetterCut { letter } => {
+                let count = letter as usize % (DECK_SIZE - 1);
+                let letter = match letter {
+                    1..=26 => format!(
+                        "the letter {}, worth {}",
+                        (b'A' + letter - 1) as char,
+                        count
+                    ),
+                    _ => format!("the value {}", letter),
+                };
+                (
+                    format!(
+                        "For {}, cut below the {} card, keeping the bottom card in place.",
+                        letter,
+                        ordinal(count)
+                    ),
+                    self.ends(after),
+                )
+            }
+            Step::PlaceJoker { joker, n } => {
+                let card = Deck::<DECK_SIZE>::joker(joker);
+                let n = n as usize % (DECK_SIZE - 1);
+                let text = match n {
+                    0 => format!("Move joker {:?} to the top of the deck.", joker),
+                    _ => format!(
+                        "Set joker {:?} aside, then put it back directly below the {} card.",
+                        joker,
+                        ordinal(n)
+                    ),
+                };
+                (text, self.surroundings(after, card))
+            }
+            Step::Output { value } => {
+                let letter = value_letter(value);
+                (
+                    format!("{}, so output {}.", self.count_down(before), letter),
+                    format!("the output is {} ({})", letter, value),
+                )
+            }
+            Step::SkipJoker { .. } => (
+                format!(
+                    "{}, which is a joker, so there is no output: repeat from the first step.",
+                    self.count_down(before)
+                ),
+                "the deck is unchanged".to_string(),
+            ),
+        }
+    }
+}
+
+impl<const DECK_SIZE: usize> Observer<DECK_SIZE> for Instructions<DECK_SIZE> {
+    fn observe(&mut self, step: Step, deck: &Deck<DECK_SIZE>) {
+        let (text, result) = self.describe(step, deck);
+        self.instructions.push(Instruction { step, text, result });
+        self.deck = deck.clone();
+    }
+}
+
+impl<const DECK_SIZE: usize> fmt::Display for Instructions<DECK_SIZE> {
+    /// Write a numbered list, with the expected result below each instruction.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.instructions.len().to_string().len();
+        for (idx, instruction) in self.instructions.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>width$}. {}",
+                idx + 1,
+                instruction.text,
+                width = width
+            )?;
+            writeln!(
+                f,
+                "{:>width$}  Now {}.",
+                "",
+                instruction.result,
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ordinal() {
+        let ordinals: Vec<_> = [1, 2, 3, 4, 11, 12, 13, 21, 22, 53]
+            .iter()
+            .map(|&n| ordinal(n))
+            .collect();
+        assert_eq!(
+            ordinals,
+            ["1st", "2nd", "3rd", "4th", "11th", "12th", "13th", "21st", "22nd", "53rd"]
+        );
+    }
+
+    #[test]
+    fn test_first_output() {
+        let instructions = Instructions::for_keystream(Deck::<DEFAULT_DECK_SIZE>::new(), 1);
+        let texts: Vec<_> = instructions
+            .instructions()
+            .iter()
+            .map(|instruction| instruction.text.as_str())
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                "Move joker A (currently 53rd) down one, to 54th.",
+                "Move joker B (currently 53rd) down two, passing the bottom card to continue \
+                 from the top, to 2nd.",
+                "Triple cut: move the one card above the upper joker to the bottom.",
+                "The bottom card is 1♧, worth 1: cut below the 1st card, keeping the bottom \
+                 card in place.",
+                "The top card is 2♧, worth 2: count two cards from the top; the 3rd card is \
+                 4♧, so output D.",
+            ]
+        );
+        assert_eq!(
+            instructions.instructions()[1].result,
+            "BJ is 2nd, between 1♧ and 2♧"
+        );
+    }
+
+    #[test]
+    fn test_keying() {
+        let instructions = Instructions::<DEFAULT_DECK_SIZE>::for_passphrase(
+            "ab",
+            KeyingOptions { place_jokers: true },
+        );
+        let steps: Vec<_> = instructions
+            .instructions()
+            .iter()
+            .map(|instruction| instruction.step)
+            .collect();
+        assert_eq!(steps.len(), 2 * 5 + 2);
+        assert_eq!(
+            instructions.instructions()[4].text,
+            "For the letter A, worth 1, cut below the 1st card, keeping the bottom card in place."
+        );
+        assert_eq!(
+            instructions.instructions()[11].text,
+            "Set joker B aside, then put it back directly below the 2nd card."
+        );
+        assert!(instructions.to_string().starts_with(" 1. Move joker A"));
+    }
+}
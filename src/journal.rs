@@ -0,0 +1,217 @@
+//! A record of which keystream has already been used.
+//!
+//! Encrypting two messages with the same keystream is catastrophic for a
+//! stream cipher: anyone holding both ciphertexts can cancel the keystream
+//! out. A [`Journal`] remembers, for each starting deck, which ranges of its
+//! keystream have been consumed, so that reuse can be caught before it
+//! happens.
+//!
+//! Decks are identified only by a fingerprint, so the journal never holds a
+//! key directly. A [`Fingerprint`] is not a cryptographic hash, though: it
+//! lets a passphrase be guessed offline, so the journal must be kept as
+//! secret as the keys it records. It is stored as plain text, one range per
+//! line:
+//!
+//! ```text
+//! <fingerprint, in hex> <start> <end>
+//! ```
+//!
+//! Ranges are half-open, and count keystream values from the starting deck.
+//! Blank lines and lines beginning with `#` are ignored.
+
+use crate::{Deck, Fingerprint};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum JournalError {
+    #[error("could not access the journal")]
+    Io(#[from] io::Error),
+    #[error("line {line} of the journal is malformed: {text:?}")]
+    Malformed { line: usize, text: String },
+}
+
+/// A range of keystream which has been used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// the fingerprint of the deck from which the keystream began
+    pub fingerprint: Fingerprint,
+    /// the keystream values consumed, counted from the starting deck
+    pub range: Range<usize>,
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.fingerprint, self.range.start, self.range.end
+        )
+    }
+}
+
+impl FromStr for Entry {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split_whitespace();
+        let mut next = || fields.next().ok_or(());
+        let fingerprint = next()?.parse().map_err(|_| ())?;
+        let start = next()?.parse().map_err(|_| ())?;
+        let end = next()?.parse().map_err(|_| ())?;
+        if fields.next().is_some() || end < start {
+            return Err(());
+        }
+        Ok(Entry {
+            fingerprint,
+            range: start..end,
+        })
+    }
+}
+
+/// A record of the keystream ranges used from each starting deck.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Journal {
+    entries: Vec<Entry>,
+}
+
+impl Journal {
+    /// Create a new, empty journal.
+    pub fn new() -> Journal {
+        Journal::default()
+    }
+
+    /// Load a journal from a file.
+    ///
+    /// A missing file is an empty journal.
+    pub fn load(path: impl AsRef<Path>) -> Result<Journal, JournalError> {
+        match fs::read_to_string(path) {
+            Ok(text) => text.parse(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Journal::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Save this journal to a file, replacing its contents.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), JournalError> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    /// the ranges recorded, in the order they were first used
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Find a recorded range of keystream from `deck` which overlaps `range`.
+    pub fn overlapping(&self, deck: &Deck, range: Range<usize>) -> Option<Range<usize>> {
+        let fingerprint = deck.fingerprint();
+        self.entries
+            .iter()
+            .filter(|entry| entry.fingerprint == fingerprint)
+            .find(|entry| entry.range.start < range.end && range.start < entry.range.end)
+            .map(|entry| entry.range.clone())
+    }
+
+    /// Record that `range` of the keystream from `deck` has been used.
+    ///
+    /// Ranges which overlap or touch are merged.
+    pub fn record(&mut self, deck: &Deck, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        let fingerprint = deck.fingerprint();
+        let mut merged = range;
+        self.entries.retain(|entry| {
+            let touches = entry.fingerprint == fingerprint
+                && entry.range.start <= merged.end
+                && merged.start <= entry.range.end;
+            if touches {
+                merged = merged.start.min(entry.range.start)..merged.end.max(entry.range.end);
+            }
+            !touches
+        });
+        self.entries.push(Entry {
+            fingerprint,
+            range: merged,
+        });
+    }
+}
+
+impl fmt::Display for Journal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Journal {
+    type Err = JournalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut journal = Journal::new();
+        for (idx, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let entry: Entry = line.parse().map_err(|_| JournalError::Malformed {
+                line: idx + 1,
+                text: line.to_string(),
+            })?;
+            journal.entries.push(entry);
+        }
+        Ok(journal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlapping() {
+        let deck = Deck::from_passphrase("journal");
+        let mut journal = Journal::new();
+        journal.record(&deck, 0..10);
+        assert_eq!(journal.overlapping(&deck, 5..15), Some(0..10));
+        assert_eq!(journal.overlapping(&deck, 10..15), None);
+        assert_eq!(journal.overlapping(&Deck::new(), 0..10), None);
+    }
+
+    #[test]
+    fn test_record_merges() {
+        let deck = Deck::from_passphrase("journal");
+        let mut journal = Journal::new();
+        journal.record(&deck, 0..10);
+        journal.record(&Deck::new(), 0..5);
+        journal.record(&deck, 20..30);
+        journal.record(&deck, 10..20);
+        assert_eq!(journal.entries().len(), 2);
+        assert_eq!(journal.overlapping(&deck, 29..40), Some(0..30));
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut journal = Journal::new();
+        journal.record(&Deck::from_passphrase("journal"), 0..10);
+        journal.record(&Deck::new(), 3..5);
+        let text = format!("# used keystream\n\n{}", journal);
+        assert_eq!(text.parse::<Journal>().unwrap(), journal);
+    }
+
+    #[test]
+    fn test_malformed() {
+        match "0123 1".parse::<Journal>() {
+            Err(JournalError::Malformed { line: 1, .. }) => {}
+            other => panic!("expected a malformed line; got {:?}", other),
+        }
+    }
+}
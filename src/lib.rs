@@ -1,10 +1,32 @@
+pub mod alphabet;
 pub mod card;
+pub mod checkpoint;
+pub mod config;
 pub mod deck;
+pub mod diagnose;
+pub mod fingerprint;
+pub mod instructions;
+pub mod journal;
+pub mod rank;
+pub mod session;
+pub mod stream;
 pub mod textbyte;
+pub mod trace;
 
-use card::{JOKER_A, JOKER_B};
+pub use alphabet::Alphabet;
+use card::Joker;
+pub use checkpoint::{Checkpoint, Checkpoints};
+pub use config::{CipherConfig, Padding};
 pub use deck::Deck;
+use deck::DEFAULT_DECK_SIZE;
+pub use fingerprint::Fingerprint;
+pub use journal::Journal;
+use rand::{CryptoRng, Rng};
+pub use session::Session;
+pub use stream::{SolitaireReader, SolitaireWriter};
 use textbyte::prelude::*;
+use thiserror::Error;
+use trace::{Observer, Step};
 
 /// size of groups of output characters
 pub const GROUP_SIZE: usize = 5;
@@ -12,12 +34,71 @@ pub const GROUP_SIZE: usize = 5;
 pub const PAD_CHAR: u8 = b'X' - b'A' + 1;
 
 /// A Keystream is an iterator which mutates a card deck to generate an infinite
-/// pseudo-random stream of characters in the range `'A'..='Z'`.
-pub struct Keystream(Deck);
+/// pseudo-random stream of values.
+///
+/// Any deck size with room for two jokers works; the values produced are in
+/// the range `1..=DECK_SIZE - 2`.
+#[derive(Clone)]
+pub struct Keystream<const DECK_SIZE: usize = DEFAULT_DECK_SIZE> {
+    deck: Deck<DECK_SIZE>,
+    /// how many values have been produced
+    position: usize,
+    /// how many rounds have output a joker, and so produced no value
+    skipped: usize,
+    /// what is needed to step back, if the keystream is rewindable
+    history: Option<History>,
+}
+
+/// the record kept by a rewindable keystream
+#[derive(Clone, Default)]
+struct History {
+    /// the earliest position to which the keystream can step back
+    earliest: usize,
+    /// how many rounds can be undone by stepping back
+    rounds: usize,
+    /// each round in which a joker was pushed from the bottom of the deck,
+    /// with which jokers were, so that those rounds can be undone exactly
+    from_bottom: Vec<(usize, [bool; 2])>,
+}
+
+impl History {
+    fn starting_at(earliest: usize) -> History {
+        History {
+            earliest,
+            ..History::default()
+        }
+    }
+
+    /// note that a round was played
+    fn record(&mut self, from_bottom: [bool; 2]) {
+        if from_bottom.contains(&true) {
+            self.from_bottom.push((self.rounds, from_bottom));
+        }
+        self.rounds += 1;
+    }
+
+    /// undo the most recent round
+    fn unround<const DECK_SIZE: usize>(&mut self, deck: &mut Deck<DECK_SIZE>) {
+        self.rounds -= 1;
+        let from_bottom = match self.from_bottom.last() {
+            Some(&(round, from_bottom)) if round == self.rounds => {
+                self.from_bottom.pop();
+                from_bottom
+            }
+            _ => [false; 2],
+        };
+        deck.unstep(from_bottom);
+    }
+}
 
-impl From<Deck> for Keystream {
-    fn from(deck: Deck) -> Self {
-        Keystream(deck)
+impl<const DECK_SIZE: usize> From<Deck<DECK_SIZE>> for Keystream<DECK_SIZE> {
+    fn from(deck: Deck<DECK_SIZE>) -> Self {
+        Keystream {
+            deck,
+            position: 0,
+            skipped: 0,
+            history: None,
+        }
     }
 }
 
@@ -25,49 +106,413 @@ pub fn keystream(deck: Deck) -> Keystream {
     deck.into()
 }
 
-impl Iterator for Keystream {
+impl<const DECK_SIZE: usize> Keystream<DECK_SIZE> {
+    /// the current state of the deck
+    pub fn deck(&self) -> &Deck<DECK_SIZE> {
+        &self.deck
+    }
+
+    /// mutable access to the deck, for re-keying mid-stream
+    ///
+    /// Re-keying cannot be undone, so the keystream cannot step back past this.
+    pub(crate) fn deck_mut(&mut self) -> &mut Deck<DECK_SIZE> {
+        if let Some(history) = &mut self.history {
+            *history = History::starting_at(self.position);
+        }
+        &mut self.deck
+    }
+
+    /// Keep a record of the rounds played from here on, so that the keystream
+    /// can step back to this position.
+    ///
+    /// The record grows by a few bytes for every round a joker is pushed from
+    /// the bottom of the deck, so it is not kept unless asked for.
+    pub fn rewindable(mut self) -> Self {
+        if self.history.is_none() {
+            self.history = Some(History::starting_at(self.position));
+        }
+        self
+    }
+
+    /// how many values have been produced since the keystream began
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// how many times the output card was a joker since the keystream began
+    ///
+    /// Each of these cost a round of the algorithm without producing a value.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// Record the current state, from which the keystream can be resumed.
+    pub fn checkpoint(&self) -> Checkpoint<DECK_SIZE> {
+        Checkpoint {
+            position: self.position,
+            skipped: self.skipped,
+            deck: self.deck.clone(),
+        }
+    }
+
+    /// stop generating keystream, returning the current state of the deck
+    pub fn into_deck(self) -> Deck<DECK_SIZE> {
+        self.deck
+    }
+}
+
+impl<const DECK_SIZE: usize> Keystream<DECK_SIZE> {
+    /// Produce the next value, showing the deck to `observer` after every step.
+    ///
+    /// Whenever the output card is a joker, the observer sees it skipped
+    /// before the steps are repeated.
+    pub fn next_observed(&mut self, observer: &mut impl Observer<DECK_SIZE>) -> u8 {
+        let deck = &mut self.deck;
+        loop {
+            let from_bottom = deck.step_observed(observer);
+            if let Some(history) = &mut self.history {
+                history.record(from_bottom);
+            }
+            let card = deck.output_card();
+            let step = if card as usize == DECK_SIZE - 1 {
+                Step::SkipJoker { joker: Joker::A }
+            } else if card as usize == DECK_SIZE {
+                Step::SkipJoker { joker: Joker::B }
+            } else {
+                Step::Output { value: card }
+            };
+            observer.observe(step, deck);
+            match step {
+                Step::Output { value } => {
+                    self.position += 1;
+                    return value;
+                }
+                _ => self.skipped += 1,
+            }
+        }
+    }
+}
+
+impl<const DECK_SIZE: usize> Keystream<DECK_SIZE> {
+    /// Undo producing the most recent value, returning it.
+    ///
+    /// The deck is restored exactly as it was before that value was produced,
+    /// including any rounds whose output card was a joker, so the value is
+    /// produced again next. Returns `None` unless the keystream is
+    /// [rewindable](Keystream::rewindable), and once back at the position
+    /// where it was made so, or at the last time the deck was re-keyed.
+    pub fn step_back(&mut self) -> Option<u8> {
+        let history = self.history.as_mut()?;
+        if history.rounds == 0 {
+            return None;
+        }
+        let value = self.deck.output();
+        history.unround(&mut self.deck);
+        // the rounds before this value output a joker, if any
+        while history.rounds > 0 && self.deck.output().is_none() {
+            history.unround(&mut self.deck);
+            self.skipped -= 1;
+        }
+        self.position -= 1;
+        value
+    }
+
+    /// Generate or undo keystream until `position` values have been produced.
+    ///
+    /// Seeking backwards steps back one value at a time, so needs the
+    /// keystream to be [rewindable](Keystream::rewindable).
+    pub fn skip_to(&mut self, position: usize) -> Result<(), SeekError> {
+        let earliest = self
+            .history
+            .as_ref()
+            .map_or(self.position, |history| history.earliest);
+        if position < earliest {
+            return Err(SeekError::Unreachable { position, earliest });
+        }
+        while self.position > position {
+            self.step_back();
+        }
+        while self.position < position {
+            self.next_observed(&mut ());
+        }
+        Ok(())
+    }
+}
+
+impl<const DECK_SIZE: usize> Iterator for Keystream<DECK_SIZE> {
     type Item = u8;
     fn next(&mut self) -> Option<Self::Item> {
-        let deck = &mut self.0;
-        let mut output = None;
-        while output.is_none() {
-            deck.push(JOKER_A, 1);
-            deck.push(JOKER_B, 2);
-            deck.triple_cut(JOKER_A, JOKER_B);
-            deck.count_cut(None);
-            output = deck.output();
+        Some(self.next_observed(&mut ()))
+    }
+}
+
+impl<const DECK_SIZE: usize> From<Checkpoint<DECK_SIZE>> for Keystream<DECK_SIZE> {
+    /// Resume the keystream from which a checkpoint was recorded.
+    ///
+    /// Even if made rewindable, it cannot step back before the checkpoint.
+    fn from(checkpoint: Checkpoint<DECK_SIZE>) -> Self {
+        Keystream {
+            position: checkpoint.position,
+            skipped: checkpoint.skipped,
+            ..Keystream::from(checkpoint.deck)
         }
-        output.map(u8::from)
     }
 }
 
-/// encrypt some plaintext using a pre-prepared deck
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum SeekError {
+    #[error("cannot seek to position {position}: the keystream can step back only to {earliest}")]
+    Unreachable { position: usize, earliest: usize },
+}
+
+/// encrypt some plaintext using an existing keystream
 ///
-/// Note that the deck is consumed. Prepare your entire message before
-/// calling this method. Solitaire is not recommended for long messages.
-fn crypt(deck: Deck, text: &str, operation: impl Fn(u8, u8) -> u8) -> String {
-    textbyte(text)
-        .pad(PAD_CHAR, GROUP_SIZE)
-        .zip(keystream(deck))
-        .map(|(c, k)| operation(c, k))
-        .restore()
-        .separate(' ', GROUP_SIZE)
+/// The keystream advances by exactly as many characters as are produced,
+/// so a subsequent call continues where this one left off. When encrypting,
+/// any indicator is drawn from `rng`.
+pub(crate) fn crypt<R: Rng + CryptoRng>(
+    keystream: &mut Keystream,
+    config: &CipherConfig,
+    text: &str,
+    operation: Operation,
+    rng: &mut R,
+) -> Result<String, CipherError> {
+    let (mut text, trailing) = config.split_trailing(text);
+    let mut expected_tag = None;
+    if config.tag_length > 0 && operation == Operation::Decrypt {
+        let (body, tag) = config.split_tag(text)?;
+        text = body;
+        expected_tag = Some(tag);
+    }
+
+    let mut out = String::new();
+    if config.indicator_length > 0 {
+        let indicator = match operation {
+            Operation::Encrypt => {
+                let indicator = config.alphabet.random_values(config.indicator_length, rng);
+                out.extend(indicator.iter().copied().restore_with(&config.alphabet));
+                out.push(config.separator);
+                indicator
+            }
+            Operation::Decrypt => {
+                let (indicator, body) = config.split_indicator(text)?;
+                text = body;
+                indicator
+            }
+        };
+        keystream.deck_mut().key_values(indicator);
+    }
+
+    let mut plaintext = Vec::new();
+    out.push_str(&crypt_body(
+        keystream,
+        config,
+        text,
+        operation,
+        &mut plaintext,
+    )?);
+
+    if config.tag_length > 0 {
+        let tag = authenticate(keystream, config, plaintext);
+        match expected_tag {
+            Some(expected) if expected != tag => return Err(CipherError::TagMismatch),
+            Some(_) => {}
+            None => {
+                out.push(config.separator);
+                out.extend(tag.into_iter().restore_with(&config.alphabet));
+            }
+        }
+    }
+    out.push_str(trailing);
+    Ok(out)
+}
+
+/// compute the authentication tag of a message
+///
+/// After the body, the deck keeps absorbing the plaintext, cutting on each
+/// letter just as [`Deck::from_passphrase`] does, then the tag is drawn from
+/// the keystream.
+pub(crate) fn authenticate(
+    keystream: &mut Keystream,
+    config: &CipherConfig,
+    plaintext: impl IntoIterator<Item = u8>,
+) -> Vec<u8> {
+    keystream.deck_mut().key_values(plaintext);
+    keystream
+        .take(config.tag_length)
+        .map(|k| Operation::Encrypt.apply(1, k, config.alphabet.size()))
+        .collect()
+}
+
+/// encrypt the body of a message, between any indicator and tag
+///
+/// The plaintext values of the body, including any padding, are appended to
+/// `plaintext`.
+fn crypt_body(
+    keystream: &mut Keystream,
+    config: &CipherConfig,
+    text: &str,
+    operation: Operation,
+    plaintext: &mut Vec<u8>,
+) -> Result<String, CipherError> {
+    let alphabet = &config.alphabet;
+    if config.preserve {
+        return Ok(crypt_preserving(
+            keystream, alphabet, text, operation, plaintext,
+        ));
+    }
+    let mut values: Vec<u8> = alphabet.textbyte(text).collect();
+    // fill padding is applied in both directions, so that short ciphertext
+    // still decrypts to whole groups
+    if operation == Operation::Encrypt || config.padding == Padding::Fill {
+        values.extend(config.padding_for(values.len()));
+    }
+    if operation == Operation::Encrypt {
+        plaintext.extend_from_slice(&values);
+    }
+    for (value, key) in values.iter_mut().zip(keystream) {
+        *value = operation.apply(*value, key, alphabet.size());
+    }
+    if operation == Operation::Decrypt {
+        plaintext.extend_from_slice(&values);
+        config.unpad(&mut values)?;
+    }
+    Ok(config.separate(values.into_iter().restore_with(alphabet)))
+}
+
+/// encrypt some text using an existing keystream, preserving its format
+///
+/// Only symbols of the alphabet are transformed, keeping their case; every
+/// other char is copied unchanged. No padding is added and the output is not
+/// grouped, so the transformation is lossless.
+fn crypt_preserving(
+    keystream: &mut Keystream,
+    alphabet: &Alphabet,
+    text: &str,
+    operation: Operation,
+    plaintext: &mut Vec<u8>,
+) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match alphabet.cased_value(c) {
+            Some(value) => {
+                let key = keystream.next().expect("keystream is an infinite iterator");
+                let output = operation.apply(value, key, alphabet.size());
+                plaintext.push(match operation {
+                    Operation::Encrypt => value,
+                    Operation::Decrypt => output,
+                });
+                alphabet.push_symbol_like(&mut out, output, c);
+            }
+            None => out.push(c),
+        }
+    }
+    out
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum CipherError {
+    #[error("the padding of the message is malformed")]
+    MalformedPadding,
+    #[error("the message is too short to contain its indicator")]
+    MissingIndicator,
+    #[error("the message is too short to contain its authentication tag")]
+    MissingTag,
+    #[error("the message has been altered: its authentication tag does not match")]
+    TagMismatch,
+}
+
+/// Whether a keystream is added to or subtracted from its input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Encrypt,
+    Decrypt,
+}
+
+impl Operation {
+    /// combine a single text value with a single keystream value
+    ///
+    /// Both the text value and the output are in the range `1..=modulus`;
+    /// the keystream value is reduced modulo `modulus`.
+    pub(crate) fn apply(self, text: u8, key: u8, modulus: usize) -> u8 {
+        let text = text as usize - 1;
+        let key = key as usize % modulus;
+        let out = match self {
+            Operation::Encrypt => text + key,
+            Operation::Decrypt => text + modulus - key,
+        };
+        (out % modulus) as u8 + 1
+    }
 }
 
 /// encrypt some plaintext using a pre-prepared deck
 ///
 /// Note that the deck is consumed. Prepare the entire message before
 /// calling this method. Solitaire is not recommended for long messages.
+/// To continue the keystream across several messages, use a [`Session`].
 pub fn encrypt(deck: Deck, text: &str) -> String {
-    crypt(deck, text, |p, k| p + k)
+    encrypt_with(deck, text, &CipherConfig::default())
+}
+
+/// encrypt some plaintext using a pre-prepared deck and a custom configuration
+pub fn encrypt_with(deck: Deck, text: &str, config: &CipherConfig) -> String {
+    encrypt_with_rng(deck, text, config, &mut rand::thread_rng())
+}
+
+/// encrypt some plaintext, drawing any indicator from the given source of randomness
+pub fn encrypt_with_rng<R: Rng + CryptoRng>(
+    deck: Deck,
+    text: &str,
+    config: &CipherConfig,
+    rng: &mut R,
+) -> String {
+    crypt(&mut keystream(deck), config, text, Operation::Encrypt, rng)
+        .expect("encryption cannot fail")
 }
 
 /// decrypt some ciphertext using a pre-prepared deck
 ///
 /// Note that the deck is consumed. Prepare the entire message before
 /// calling this method. Solitaire is not recommended for long messages.
+/// To continue the keystream across several messages, use a [`Session`].
 pub fn decrypt(deck: Deck, text: &str) -> String {
-    crypt(deck, text, |c, k| c + (26 * 3) - k)
+    decrypt_with(deck, text, &CipherConfig::default()).expect("fill padding is never malformed")
+}
+
+/// decrypt some ciphertext using a pre-prepared deck and a custom configuration
+///
+/// Fails if the configuration's padding can be verified and is malformed.
+pub fn decrypt_with(deck: Deck, text: &str, config: &CipherConfig) -> Result<String, CipherError> {
+    crypt(
+        &mut keystream(deck),
+        config,
+        text,
+        Operation::Decrypt,
+        &mut rand::thread_rng(),
+    )
+}
+
+/// encrypt the letters of some plaintext, preserving everything else
+///
+/// Case, spacing, and punctuation are kept, and no padding is added.
+/// [`decrypt_preserving`] reproduces the original text exactly.
+pub fn encrypt_preserving(deck: Deck, text: &str) -> String {
+    let config = CipherConfig::builder()
+        .preserve_format(true)
+        .build()
+        .expect("format-preserving config is valid");
+    encrypt_with(deck, text, &config)
+}
+
+/// decrypt the letters of some ciphertext, preserving everything else
+///
+/// This is the inverse of [`encrypt_preserving`].
+pub fn decrypt_preserving(deck: Deck, text: &str) -> String {
+    let config = CipherConfig::builder()
+        .preserve_format(true)
+        .build()
+        .expect("format-preserving config is valid");
+    decrypt_with(deck, text, &config).expect("format-preserving decryption cannot fail")
 }
 
 #[cfg(test)]
@@ -75,6 +520,14 @@ mod tests {
     use super::*;
     use rstest::rstest;
 
+    const MESSAGES: &[&str] = &[
+        "The quick brown fox jumps over the lazy dog.",
+        "Supercalifragilisticexpialidocious",
+        "Two tires fly. Two wail. A bamboo grove, all chopped down. From it, warring songs.",
+        "Let's set the existence-of-god issue aside for a later volume, and just stipulate that in _some_ way, self-replicating organisms came into existence on this planet and immediately began trying to get rid of each other, either by spamming their environments with rough copies of themselves, or by more direct means which hardly need to be belabored.",
+        "For a long time there is really nothing to be seen except steam; but after Golgotha's been burning for an hour or two, it becomes possible to see that underneath the shallow water, spreading down the valley floor, indeed right around the isolated boulder where Randy's perched, is a bright, thick river of gold.",
+    ];
+
     const DECK_SIZE: usize = crate::deck::DEFAULT_DECK_SIZE;
 
     #[test]
@@ -169,15 +622,12 @@ mod tests {
 
     #[test]
     fn test_reverse_message() {
-        for w in [
-            "The quick brown fox jumps over the lazy dog.",
-            "Supercalifragilisticexpialidocious",
-            "Two tires fly. Two wail. A bamboo grove, all chopped down. From it, warring songs.",
-            "Let's set the existence-of-god issue aside for a later volume, and just stipulate that in _some_ way, self-replicating organisms came into existence on this planet and immediately began trying to get rid of each other, either by spamming their environments with rough copies of themselves, or by more direct means which hardly need to be belabored.",
-            "For a long time there is really nothing to be seen except steam; but after Golgotha's been burning for an hour or two, it becomes possible to see that underneath the shallow water, spreading down the valley floor, indeed right around the isolated boulder where Randy's perched, is a bright, thick river of gold.",
-        ].windows(2) {
+        for w in MESSAGES.windows(2) {
             let (key, msg) = (w[0], w[1]);
-            let expect: String = textbyte(msg).pad(PAD_CHAR, GROUP_SIZE).restore().separate(' ', GROUP_SIZE);
+            let expect: String = textbyte(msg)
+                .pad(PAD_CHAR, GROUP_SIZE)
+                .restore()
+                .separate(' ', GROUP_SIZE);
             let deck = Deck::from_passphrase(key);
             let ciphertext = encrypt(deck.clone(), msg);
             let plaintext = decrypt(deck, &ciphertext);
@@ -186,6 +636,297 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_reverse_message_preserving() {
+        for w in MESSAGES.windows(2) {
+            let (key, msg) = (w[0], w[1]);
+            let deck = Deck::from_passphrase(key);
+            let ciphertext = encrypt_preserving(deck.clone(), msg);
+            assert_ne!(ciphertext, msg);
+            assert_eq!(decrypt_preserving(deck, &ciphertext), msg);
+        }
+    }
+
+    #[test]
+    fn test_preserving_irregular_case() {
+        // each of these upper-cases to a symbol, but is not its lower case
+        let deck = Deck::from_passphrase("case");
+        let msg = "Straſse ıs Σοφός";
+        let ciphertext = encrypt_preserving(deck.clone(), msg);
+        assert_eq!(ciphertext.chars().nth(4), Some('ſ'));
+        assert_eq!(ciphertext.chars().nth(8), Some('ı'));
+        assert_eq!(decrypt_preserving(deck.clone(), &ciphertext), msg);
+
+        let config = CipherConfig::builder()
+            .alphabet(Alphabet::greek())
+            .preserve_format(true)
+            .build()
+            .unwrap();
+        let ciphertext = encrypt_with(deck.clone(), msg, &config);
+        assert!(ciphertext.ends_with('ς'));
+        assert_eq!(decrypt_with(deck, &ciphertext, &config).unwrap(), msg);
+    }
+
+    #[test]
+    fn test_encrypt_preserving_example() {
+        // the same keystream as "KIRAK SFJAN", without the padding
+        assert_eq!(
+            encrypt_preserving(Deck::from_passphrase("cryptonomicon"), "Soli-taire!"),
+            "Kira-ksfja!",
+        );
+    }
+
+    #[test]
+    fn test_radio_procedure() {
+        let config = CipherConfig::builder()
+            .group_size(4)
+            .pad('q')
+            .build()
+            .unwrap();
+        let deck = Deck::from_passphrase("cryptonomicon");
+        let ciphertext = encrypt_with(deck.clone(), "solitaire", &config);
+        assert_eq!(ciphertext, "KIRA KSFJ AGHC");
+        assert_eq!(
+            decrypt_with(deck, &ciphertext, &config).unwrap(),
+            "SOLI TAIR EQQQ"
+        );
+    }
+
+    #[test]
+    fn test_length_marked_padding() {
+        let config = CipherConfig::builder()
+            .padding(Padding::LengthMarked)
+            .build()
+            .unwrap();
+        let deck = Deck::from_passphrase("cryptonomicon");
+        let ciphertext = encrypt_with(deck.clone(), "solitairex", &config);
+        assert_eq!(ciphertext.len(), 17);
+        assert_eq!(
+            decrypt_with(deck.clone(), &ciphertext, &config).unwrap(),
+            "SOLIT AIREX"
+        );
+        let ciphertext = encrypt_with(deck.clone(), "solitaire", &config);
+        assert_eq!(&ciphertext[..10], "KIRAK SFJA");
+        assert_eq!(
+            decrypt_with(deck.clone(), &ciphertext, &config).unwrap(),
+            "SOLIT AIRE"
+        );
+        // the final symbol of the default-padded message marks 24 symbols of padding
+        assert_eq!(
+            decrypt_with(deck, "kirak sfjan", &config),
+            Err(CipherError::MalformedPadding)
+        );
+    }
+
+    #[test]
+    fn test_ungrouped() {
+        let config = CipherConfig::builder().group_size(0).build().unwrap();
+        let deck = Deck::from_passphrase("cryptonomicon");
+        assert_eq!(encrypt_with(deck, "solitaire", &config), "KIRAKSFJA");
+    }
+
+    #[test]
+    fn test_indicator() {
+        let config = CipherConfig::builder().indicator_length(5).build().unwrap();
+        let deck = Deck::from_passphrase("cryptonomicon");
+        let first = encrypt_with(deck.clone(), "solitaire", &config);
+        let second = encrypt_with(deck.clone(), "solitaire", &config);
+        assert_ne!(first, second, "indicators should differ between messages");
+        for ciphertext in &[first, second] {
+            let (indicator, body) = ciphertext.split_at(5);
+            let keyed = Deck::from_passphrase(&format!("cryptonomicon{}", indicator));
+            assert_eq!(&encrypt(keyed, "solitaire"), &body[1..]);
+            assert_eq!(
+                decrypt_with(deck.clone(), ciphertext, &config).unwrap(),
+                "SOLIT AIREX"
+            );
+        }
+        assert_eq!(
+            decrypt_with(deck, "abc", &config),
+            Err(CipherError::MissingIndicator)
+        );
+    }
+
+    #[test]
+    fn test_indicator_rng() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let config = CipherConfig::builder().indicator_length(5).build().unwrap();
+        let deck = Deck::from_passphrase("indicator");
+        let encrypt = |seed| {
+            encrypt_with_rng(
+                deck.clone(),
+                "solitaire",
+                &config,
+                &mut StdRng::seed_from_u64(seed),
+            )
+        };
+        assert_eq!(encrypt(1), encrypt(1));
+        assert_ne!(encrypt(1), encrypt(2));
+        assert_eq!(
+            decrypt_with(deck.clone(), &encrypt(1), &config).unwrap(),
+            "SOLIT AIREX"
+        );
+    }
+
+    #[test]
+    fn test_indicator_preserving() {
+        let config = CipherConfig::builder()
+            .indicator_length(4)
+            .preserve_format(true)
+            .build()
+            .unwrap();
+        let deck = Deck::from_passphrase("indicator");
+        let msg = "Meet me at 10:00, by the old mill.";
+        let ciphertext = encrypt_with(deck.clone(), msg, &config);
+        assert_eq!(ciphertext.len(), msg.len() + 5);
+        assert_eq!(decrypt_with(deck, &ciphertext, &config).unwrap(), msg);
+    }
+
+    #[test]
+    fn test_authentication() {
+        for (preserve, indicator) in &[(false, 0), (false, 5), (true, 0), (true, 5)] {
+            let config = CipherConfig::builder()
+                .tag_length(5)
+                .preserve_format(*preserve)
+                .indicator_length(*indicator)
+                .build()
+                .unwrap();
+            let deck = Deck::from_passphrase("authentic");
+            let msg = "Attack at dawn.";
+            let ciphertext = encrypt_with(deck.clone(), msg, &config);
+            let plaintext = decrypt_with(deck.clone(), &ciphertext, &config).unwrap();
+            if *preserve {
+                assert_eq!(plaintext, msg);
+            } else {
+                assert_eq!(plaintext, "ATTAC KATDA WNXXX");
+            }
+
+            // flip a single symbol in the body
+            let mut altered: Vec<char> = ciphertext.chars().collect();
+            let idx = indicator + 8;
+            altered[idx] = if altered[idx].eq_ignore_ascii_case(&'A') {
+                'B'
+            } else {
+                'A'
+            };
+            let altered: String = altered.into_iter().collect();
+            assert_eq!(
+                decrypt_with(deck.clone(), &altered, &config),
+                Err(CipherError::TagMismatch)
+            );
+            assert_eq!(
+                decrypt_with(deck, "abc", &config),
+                Err(CipherError::MissingTag)
+            );
+        }
+    }
+
+    #[test]
+    fn test_small_deck_first_output() {
+        // [1 2 3 4 5 6 7 8] -> [1 2 3 4 5 6 8 7] -> [1 8 2 3 4 5 6 7]
+        // -> [8 2 3 4 5 6 7 1] -> [2 3 4 5 6 7 8 1]; top card 2 selects 4
+        assert_eq!(Keystream::from(Deck::<8>::new()).next(), Some(4));
+    }
+
+    #[rstest(phrase, case(""), case("teaching"), case("cryptonomicon"))]
+    fn test_small_deck_keystream(phrase: &str) {
+        fn check<const N: usize>(phrase: &str) {
+            let deck = Deck::<N>::from_passphrase(phrase);
+            let mut cards = deck.cards().map(u8::from).collect::<Vec<_>>();
+            cards.sort_unstable();
+            assert_eq!(cards, (1..=N as u8).collect::<Vec<_>>());
+            assert!(Keystream::from(deck)
+                .take(100)
+                .all(|k| k >= 1 && (k as usize) <= N - 2));
+        }
+        check::<10>(phrase);
+        check::<28>(phrase);
+    }
+
+    #[rstest(phrase, case(""), case("rewind"), case("cryptonomicon"))]
+    fn test_step_back(phrase: &str) {
+        fn check<const N: usize>(phrase: &str) -> bool {
+            let mut keystream = Keystream::from(Deck::<N>::from_passphrase(phrase)).rewindable();
+            let mut decks = vec![keystream.deck().clone()];
+            let mut values = Vec::new();
+            for _ in 0..500 {
+                values.push(keystream.next().unwrap());
+                decks.push(keystream.deck().clone());
+            }
+            let from_bottom = !keystream.history.as_ref().unwrap().from_bottom.is_empty();
+
+            decks.pop();
+            while let Some(value) = keystream.step_back() {
+                assert_eq!(Some(value), values.pop());
+                assert_eq!(Some(keystream.deck()), decks.last());
+                assert_eq!(keystream.position(), decks.len() - 1);
+                decks.pop();
+            }
+            assert!(values.is_empty());
+            assert_eq!(keystream.deck(), &Deck::from_passphrase(phrase));
+            from_bottom
+        }
+        // jokers are rarely pushed from the bottom of a full deck
+        assert!(check::<8>(phrase));
+        check::<DECK_SIZE>(phrase);
+    }
+
+    #[test]
+    fn test_step_back_then_forward() {
+        let mut keystream = keystream(Deck::from_passphrase("rewind")).rewindable();
+        let values: Vec<_> = keystream.by_ref().take(20).collect();
+        for _ in 0..5 {
+            keystream.step_back();
+        }
+        assert!(keystream.take(5).eq(values[15..].iter().copied()));
+    }
+
+    #[test]
+    fn test_skip_to() {
+        let deck = Deck::from_passphrase("seek");
+        let expected: Vec<_> = keystream(deck.clone()).take(50).collect();
+        let mut keystream = keystream(deck).rewindable();
+        keystream.skip_to(40).unwrap();
+        assert_eq!(keystream.next(), Some(expected[40]));
+        keystream.skip_to(10).unwrap();
+        assert_eq!(keystream.position(), 10);
+        assert_eq!(keystream.next(), Some(expected[10]));
+
+        let mut resumed = Keystream::from(keystream.checkpoint()).rewindable();
+        assert_eq!(
+            resumed.skip_to(5),
+            Err(SeekError::Unreachable {
+                position: 5,
+                earliest: 11
+            })
+        );
+        assert_eq!(resumed.skipped(), keystream.skipped());
+    }
+
+    #[test]
+    fn test_plain_keystream_keeps_no_history() {
+        let mut keystream = keystream(Deck::from_passphrase("stream"));
+        keystream.by_ref().take(10_000).for_each(drop);
+        assert!(keystream.history.is_none());
+        assert_eq!(keystream.step_back(), None);
+        assert_eq!(
+            keystream.skip_to(9_999),
+            Err(SeekError::Unreachable {
+                position: 9_999,
+                earliest: 10_000
+            })
+        );
+    }
+
+    #[test]
+    fn test_generic_keystream_matches_default() {
+        let deck = Deck::<DECK_SIZE>::from_passphrase("foo");
+        assert!(Keystream::<DECK_SIZE>::from(deck.clone())
+            .take(50)
+            .eq(keystream(deck).take(50)));
+    }
+
     #[test]
     fn test_empty_key_produces_sorted_deck() {
         assert_eq!(Deck::from_passphrase(""), Deck::<DECK_SIZE>::new(),)
@@ -1,9 +1,21 @@
 use anyhow::{bail, Result};
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use rand::{rngs::OsRng, rngs::StdRng, CryptoRng, RngCore, SeedableRng};
 use solitaire::{
-    deck::{Deck, MaybeDeck},
-    decrypt, encrypt,
+    card::{CardFormat, JokerNames},
+    deck::{
+        expected_rising_sequences, riffle_variation_distance, Deck, DiceShuffle, KeyingOptions,
+        MaybeDeck, ShuffleMethod,
+    },
+    diagnose::{Replay, DEFAULT_ROUNDS},
+    instructions::Instructions,
+    rank::RankEncoding,
+    trace::{Observer, Step},
+    Alphabet, CipherConfig, Journal, Keystream, Operation, Padding, Session, SolitaireReader,
 };
+use std::io::{self, BufRead, Write};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
 
 #[derive(Debug, Parser)]
 #[command(
@@ -11,33 +23,161 @@ use solitaire::{
     about = "Bruce Schneier's Solitaire encryption algorithm."
 )]
 struct Opt {
-    /// Only emit ASCII chars instead of unicode suit symbols
+    /// Only emit ASCII chars instead of unicode suit symbols.
+    ///
+    /// This applies only to the `cards` format.
     #[arg(short, long)]
     ascii: bool,
 
+    /// How to print decks: as a list of cards in one of several notations,
+    /// or as the deck's rank among all orderings, which is much shorter.
+    /// Every format can be read back wherever a deck is expected.
+    #[arg(short, long, value_enum, default_value = "cards")]
+    format: DeckFormat,
+
     #[command(subcommand)]
     subcommand: Command,
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum DeckFormat {
+    /// ranks and suit symbols, or suit letters with `--ascii`
+    Cards,
+    /// Unicode playing card glyphs
+    Glyphs,
+    /// English names, such as `Ace of Clubs`, separated by commas for
+    /// legibility; they are read back with or without them
+    Names,
+    /// card values from 1 to 54, as in Schneier's test vectors
+    Numbers,
+    /// ranks and suit symbols, with red cards colored for a terminal
+    Color,
+    /// the deck's rank in the letters A to Z, in groups of five
+    Base26,
+    /// the deck's rank in the RFC 4648 base32 alphabet
+    Base32,
+    /// the deck's rank as a decimal number
+    Decimal,
+}
+
 #[derive(Debug, Subcommand)]
 enum Command {
     #[command(about = "shuffle a new or existing deck")]
     Shuffle {
         /// How many times to shuffle this deck.
         ///
-        /// Shuffling requires entropy, and the amount of potential entropy
-        /// in a truly random deck of cards is much larger than the amount which
-        /// will typically be inserted by a single shuffle.
+        /// A single uniform shuffle already produces a perfectly random deck.
+        /// A physical shuffle inserts much less entropy than that, so a real
+        /// deck needs several: about seven riffles, or thousands of overhand
+        /// shuffles.
         #[arg(short = 'n', long, default_value = "7")]
         iterations: u32,
 
+        /// How to shuffle: `uniform` for a perfectly random deck, or simulate
+        /// a person's `riffle`, `overhand`, or `cut`.
+        ///
+        /// When simulating, the mixing after each shuffle is reported on
+        /// stderr. For riffles, this includes the distance from a uniformly
+        /// random deck, which is the same from any known starting order.
+        /// Never use a simulated shuffle to generate a key.
+        #[arg(long, default_value = "uniform")]
+        method: ShuffleMethod,
+
+        #[command(flatten)]
+        rng: RngArgs,
+
         /// Optionally specify a starting deck. Otherwise, a fresh sorted one
         /// will form the initial state.
         #[arg(name = "deck")]
-        maybe_deck: Option<MaybeDeck>,
+        maybe_deck: Option<String>,
+
+        #[command(flatten)]
+        parsing: DeckParsing,
+    },
+    #[command(about = "generate a random deck, from the computer or from dice")]
+    Keygen {
+        /// Generate the deck from rolls of a die, rather than the computer's
+        /// randomness, so that its entropy can be verified by hand.
+        #[arg(long)]
+        dice: bool,
+
+        /// How many sides the die has. Flip a coin as a die with 2 sides.
+        #[arg(long, default_value = "6", requires = "dice")]
+        sides: u8,
+
+        #[command(flatten)]
+        rng: RngArgs,
+
+        /// The rolls, from 1 to the number of sides. Dice with at most 9
+        /// sides may be written as a run of digits. If omitted, rolls are
+        /// prompted for on stdin.
+        #[arg(requires = "dice")]
+        rolls: Vec<String>,
     },
     #[command(about = "initialize a deck from a passphrase")]
-    Passphrase { phrase: String },
+    Passphrase {
+        phrase: String,
+
+        #[command(flatten)]
+        keying: KeyingArgs,
+    },
+    #[command(
+        about = "print a short fingerprint of a deck, to confirm it without reading every card",
+        long_about = "Print a short fingerprint of a deck, to confirm it without reading every \
+                      card.\n\nThe fingerprint is not a cryptographic hash: anyone who sees it \
+                      can cheaply test guessed passphrases against it. Share it only where the \
+                      key itself would be safe."
+    )]
+    Fingerprint {
+        #[command(flatten)]
+        deck_opts: DeckOptions,
+    },
+    #[command(about = "find the step where a computation by hand went wrong")]
+    Diagnose {
+        /// The deck the person holds now.
+        #[arg(long, required_unless_present = "letters")]
+        current: Option<String>,
+
+        /// The keystream letters the person has produced so far.
+        #[arg(long, conflicts_with = "current")]
+        letters: Option<String>,
+
+        /// How many rounds of keystream generation to search for the current deck.
+        #[arg(short = 'n', long, default_value_t = DEFAULT_ROUNDS)]
+        rounds: usize,
+
+        #[command(flatten)]
+        deck_opts: DeckOptions,
+    },
+    #[command(about = "print the deck after every step, to check a computation by hand")]
+    Trace {
+        /// How many keystream values to generate.
+        ///
+        /// Keying with a passphrase is traced before the keystream.
+        #[arg(short = 'n', long, default_value = "5")]
+        count: usize,
+
+        /// Print plain-language instructions to follow with a real deck,
+        /// rather than a table of deck states.
+        #[arg(long)]
+        instructions: bool,
+
+        #[command(flatten)]
+        deck_opts: DeckOptions,
+    },
+    #[command(about = "print checkpoints along the keystream, from which it can be resumed")]
+    Checkpoints {
+        /// How many keystream values apart to record checkpoints.
+        #[arg(long, default_value = "100")]
+        every: NonZeroUsize,
+
+        /// How many keystream values to cover.
+        #[arg(short = 'n', long)]
+        length: usize,
+
+        #[command(flatten)]
+        deck_opts: DeckOptions,
+    },
     #[command(about = "encrypt a message")]
     Encrypt {
         #[command(flatten)]
@@ -50,74 +190,541 @@ enum Command {
     },
 }
 
+/// parse rolls of a die with `sides` sides, separated by whitespace or commas
+///
+/// Dice with at most 9 sides need no separators.
+fn parse_rolls(text: &str, sides: u8) -> Result<Vec<u8>> {
+    let mut rolls = Vec::new();
+    for token in text.split(|c: char| c.is_whitespace() || c == ',') {
+        if sides <= 9 {
+            for c in token.chars() {
+                match c.to_digit(10) {
+                    Some(roll) => rolls.push(roll as u8),
+                    None => bail!("{:?} is not a roll", c),
+                }
+            }
+        } else if !token.is_empty() {
+            rolls.push(token.parse()?);
+        }
+    }
+    Ok(rolls)
+}
+
+/// generate a deck from dice rolls, prompting for more on stdin as needed
+fn deck_from_dice(sides: u8, rolls: &[String]) -> Result<Deck> {
+    let mut shuffle = DiceShuffle::new(sides)?;
+    for roll in parse_rolls(&rolls.join(" "), sides)? {
+        shuffle.push(roll)?;
+    }
+    if rolls.is_empty() {
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        while !shuffle.is_complete() {
+            eprint!("at least {} more rolls needed: ", shuffle.remaining());
+            io::stderr().flush()?;
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => break,
+            };
+            for roll in parse_rolls(&line, sides)? {
+                shuffle.push(roll)?;
+            }
+        }
+    }
+    let deck = shuffle.deck()?;
+    eprintln!(
+        "used {} rolls, of which {} were rejected to avoid bias",
+        shuffle.rolls(),
+        shuffle.rejected()
+    );
+    Ok(deck)
+}
+
+/// A cryptographically secure source of randomness.
+trait KeyRng: RngCore + CryptoRng {}
+
+impl<R: RngCore + CryptoRng> KeyRng for R {}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum RngSource {
+    /// The thread-local generator, periodically reseeded by the operating system.
+    Thread,
+    /// The operating system's generator, for every random value.
+    Os,
+}
+
 #[derive(Debug, Args)]
-struct CryptOptions {
+struct RngArgs {
+    /// Where to draw randomness from.
+    #[arg(long, value_enum, default_value = "thread")]
+    rng: RngSource,
+
+    /// Draw randomness from a generator with this seed, so that the result
+    /// can be reproduced. For debugging only: never use a seeded deck as a key.
+    #[arg(long, conflicts_with = "rng")]
+    seed: Option<u64>,
+}
+
+impl RngArgs {
+    fn rng(&self) -> Box<dyn KeyRng> {
+        match (self.seed, self.rng) {
+            (Some(seed), _) => Box::new(StdRng::seed_from_u64(seed)),
+            (None, RngSource::Os) => Box::new(OsRng),
+            (None, RngSource::Thread) => Box::new(rand::thread_rng()),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct KeyingArgs {
+    /// Use the final two letters of the passphrase to place the jokers.
+    #[arg(long)]
+    place_jokers: bool,
+}
+
+impl KeyingArgs {
+    fn options(&self) -> KeyingOptions {
+        KeyingOptions {
+            place_jokers: self.place_jokers,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct DeckOptions {
     /// This deck is used as the initial state.
     #[arg(short, long, name = "deck", conflicts_with = "passphrase")]
-    maybe_deck: Option<MaybeDeck>,
+    maybe_deck: Option<String>,
+
+    #[command(flatten)]
+    parsing: DeckParsing,
 
     /// A fresh deck is generated from this passphrase.
     #[arg(short, long)]
     passphrase: Option<String>,
 
-    message: String,
+    #[command(flatten)]
+    keying: KeyingArgs,
 }
 
-impl CryptOptions {
+impl DeckOptions {
     fn deck(&self) -> Result<Deck> {
-        if let Some(ref md) = self.maybe_deck {
-            return Ok(md.clone().check()?);
+        if let Some(ref text) = self.maybe_deck {
+            return self.parsing.deck(text);
         }
 
         if let Some(ref phrase) = self.passphrase {
-            return Ok(Deck::from_passphrase(phrase));
+            return Ok(Deck::from_passphrase_with(phrase, self.keying.options()));
         }
 
         bail!("the initial deck or a passphrase is required");
     }
 }
 
+#[derive(Debug, Args)]
+struct DeckParsing {
+    /// Ignore anything in a deck which is not a card, rather than
+    /// rejecting it.
+    ///
+    /// This applies to every deck given as text, such as the current deck
+    /// to diagnose.
+    #[arg(long)]
+    lenient: bool,
+
+    /// Say which physical joker is which, as comma-separated `NAME=A` or
+    /// `NAME=B` pairs, such as `BJ=A,RJ=B`.
+    ///
+    /// By default, the red joker (`RJ`) is joker A and the black joker
+    /// (`BJ`) is joker B.
+    #[arg(long, default_value = "")]
+    jokers: JokerNames,
+}
+
+impl DeckParsing {
+    /// Parse a deck, rejecting anything which is not a card unless lenient.
+    fn deck(&self, text: &str) -> Result<Deck> {
+        let maybe_deck = if self.lenient {
+            MaybeDeck::parse_lenient_with(text, &self.jokers)?
+        } else {
+            MaybeDeck::parse_strict_with(text, &self.jokers)?
+        };
+        Ok(maybe_deck.check()?)
+    }
+}
+
+#[derive(Debug, Args)]
+struct CryptOptions {
+    #[command(flatten)]
+    deck_opts: DeckOptions,
+
+    /// The symbols to encrypt.
+    ///
+    /// Either one of `latin`, `alphanumeric`, `greek`, or `cyrillic`, or the
+    /// literal list of symbols in order, of at most 52: the largest value of
+    /// the keystream.
+    #[arg(long, default_value = "latin")]
+    alphabet: Alphabet,
+
+    /// Only transform letters, keeping their case and every other char.
+    ///
+    /// The output is neither grouped nor padded.
+    #[arg(long)]
+    preserve: bool,
+
+    /// How many symbols to emit per group. 0 disables grouping and padding.
+    #[arg(long, default_value = "5")]
+    group_size: usize,
+
+    /// The symbol with which to pad the final group. Defaults to `X`, or the
+    /// last symbol of alphabets without one.
+    #[arg(long)]
+    pad: Option<char>,
+
+    /// The char with which to separate groups.
+    #[arg(long, default_value = " ")]
+    separator: char,
+
+    /// How to pad the final group: `fill` with the pad symbol, or `length`
+    /// to mark how much padding was added, so that it can be removed exactly.
+    #[arg(long, default_value = "fill")]
+    padding: Padding,
+
+    /// Begin each message with this many random indicator symbols, with
+    /// which the deck is keyed before the message. 0 disables indicators.
+    #[arg(long, default_value = "0")]
+    indicator: usize,
+
+    /// End each message with this many authentication symbols, which are
+    /// verified on decryption. 0 disables authentication.
+    #[arg(long, default_value = "0")]
+    tag: usize,
+
+    /// How many groups to emit per line. 0 puts every group on one line.
+    #[arg(long, default_value = "0")]
+    groups_per_line: usize,
+
+    /// After the message, print the final deck state.
+    ///
+    /// Use this deck to key the next message, so that no keystream is reused.
+    #[arg(long)]
+    print_deck: bool,
+
+    /// Record the keystream used by each message in this journal file.
+    ///
+    /// Encryption refuses to reuse keystream which the journal has recorded,
+    /// and decryption marks keystream as used. Messages with an indicator
+    /// are keyed afresh, and are not journaled.
+    #[arg(long)]
+    journal: Option<PathBuf>,
+
+    /// Encrypt even if the journal records that the keystream was already
+    /// used, with only a warning.
+    #[arg(long, requires = "journal")]
+    force: bool,
+
+    /// Resume the keystream from this file of checkpoints, as printed by the
+    /// `checkpoints` command, rather than from a deck.
+    ///
+    /// The nearest checkpoint at or before `--position` is used. A journal
+    /// needs the checkpoint at position 0, which identifies the keystream.
+    #[arg(
+        long,
+        requires = "position",
+        conflicts_with_all = ["deck", "passphrase"]
+    )]
+    checkpoints: Option<PathBuf>,
+
+    /// The keystream position at which to begin, with `--checkpoints`.
+    #[arg(long, requires = "checkpoints")]
+    position: Option<usize>,
+
+    /// The message to process. If omitted, stdin is streamed to stdout.
+    message: Option<String>,
+}
+
+impl CryptOptions {
+    fn config(&self) -> Result<CipherConfig> {
+        let mut builder = CipherConfig::builder()
+            .alphabet(self.alphabet.clone())
+            .preserve_format(self.preserve)
+            .group_size(self.group_size)
+            .separator(self.separator)
+            .groups_per_line(self.groups_per_line)
+            .padding(self.padding)
+            .indicator_length(self.indicator)
+            .tag_length(self.tag);
+        if let Some(pad) = self.pad {
+            builder = builder.pad(pad);
+        }
+        Ok(builder.build()?)
+    }
+
+    /// load the journal, if one applies to this message
+    fn journal(&self) -> Result<Option<Journal>> {
+        match self.journal {
+            Some(ref path) if self.indicator == 0 => Ok(Some(Journal::load(path)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// the deck from which the keystream began, and the keystream at which
+    /// to begin the message
+    fn keystream(&self) -> Result<(Option<Deck>, Keystream)> {
+        let (path, position) = match (&self.checkpoints, self.position) {
+            (Some(path), Some(position)) => (path, position),
+            _ => {
+                let deck = self.deck_opts.deck()?;
+                return Ok((Some(deck.clone()), deck.into()));
+            }
+        };
+        let checkpoints: solitaire::Checkpoints = std::fs::read_to_string(path)?.parse()?;
+        let keystream = match checkpoints.keystream_at(position) {
+            Some(keystream) => keystream,
+            None => bail!("no checkpoint is at or before position {}", position),
+        };
+        let initial = checkpoints
+            .nearest(0)
+            .map(|checkpoint| checkpoint.deck.clone());
+        Ok((initial, keystream))
+    }
+
+    /// process the message, returning the final state of the deck
+    fn run(&self, operation: Operation) -> Result<Deck> {
+        let (initial, keystream) = self.keystream()?;
+        let start = keystream.position();
+        let config = self.config()?;
+        // the journal records keystream by the deck from which it began
+        let mut journal = match (self.journal()?, initial) {
+            (Some(journal), Some(initial)) => Some((journal, initial)),
+            (Some(_), None) => bail!("a journal needs the checkpoint at position 0"),
+            (None, _) => None,
+        };
+        if let (Some((ref journal, ref initial)), Operation::Encrypt) = (&journal, operation) {
+            if let Some(used) = journal.overlapping(initial, start..usize::MAX) {
+                let problem = format!(
+                    "keystream {}..{} from this deck has already been used",
+                    used.start, used.end
+                );
+                if !self.force {
+                    bail!("{}; use --force to reuse it anyway", problem);
+                }
+                eprintln!("warning: {}", problem);
+            }
+        }
+
+        let (used, deck) = match self.message {
+            Some(ref message) => {
+                let mut session = Session::with_config(keystream, config);
+                let output = match operation {
+                    Operation::Encrypt => session.encrypt(message),
+                    Operation::Decrypt => session.decrypt(message)?,
+                };
+                println!("{}", output);
+                (session.position(), session.into_deck())
+            }
+            None => {
+                let stdin = io::stdin();
+                let stdout = io::stdout();
+                let mut stdout = stdout.lock();
+                let mut reader =
+                    SolitaireReader::new(stdin.lock(), keystream, operation).with_config(config);
+                io::copy(&mut reader, &mut stdout)?;
+                if !self.preserve {
+                    writeln!(stdout)?;
+                }
+                (reader.position(), reader.into_inner().1)
+            }
+        };
+
+        if let (Some((ref mut journal, ref initial)), Some(ref path)) =
+            (&mut journal, &self.journal)
+        {
+            journal.record(initial, start..used);
+            journal.save(path)?;
+        }
+        Ok(deck)
+    }
+}
+
+/// Key a sorted deck with the passphrase, if any, then generate `count`
+/// keystream values, showing every step to `observer`.
+fn trace<const DECK_SIZE: usize>(
+    phrase: Option<&str>,
+    keying: KeyingOptions,
+    deck: Deck<DECK_SIZE>,
+    count: usize,
+    observer: &mut impl Observer<DECK_SIZE>,
+) {
+    let deck = match phrase {
+        Some(phrase) => Deck::from_passphrase_observed(phrase, keying, observer),
+        None => deck,
+    };
+    let mut keystream = Keystream::from(deck);
+    for _ in 0..count {
+        keystream.next_observed(observer);
+    }
+}
+
 fn main() -> Result<()> {
     use Command::*;
     let opt = Opt::parse();
 
     let ascii = opt.ascii;
-    let print_deck = |deck: &Deck| {
-        println!(
-            "{}",
-            if ascii {
-                deck.to_ascii_string()
-            } else {
-                deck.to_string()
-            }
-        );
+    let format = opt.format;
+    if ascii && !matches!(format, DeckFormat::Cards) {
+        bail!("--ascii applies only to the `cards` format");
+    }
+    let format_deck = |deck: &Deck| match format {
+        DeckFormat::Cards if ascii => deck.to_ascii_string(),
+        DeckFormat::Cards => deck.to_string(),
+        DeckFormat::Glyphs => deck.format(CardFormat::Glyph),
+        DeckFormat::Names => deck.format(CardFormat::Name),
+        DeckFormat::Numbers => deck.format(CardFormat::Number),
+        DeckFormat::Color => deck.format(CardFormat::Ansi),
+        DeckFormat::Base26 => deck.to_rank_string(RankEncoding::Base26),
+        DeckFormat::Base32 => deck.to_rank_string(RankEncoding::Base32),
+        DeckFormat::Decimal => deck.to_rank_string(RankEncoding::Decimal),
     };
+    let print_deck = |deck: &Deck| println!("{}", format_deck(deck));
 
     match opt.subcommand {
         Shuffle {
             iterations,
+            method,
+            rng,
             maybe_deck,
+            parsing,
         } => {
             let mut deck = match maybe_deck {
                 None => Deck::new(),
-                Some(md) => md.check()?,
+                Some(text) => parsing.deck(&text)?,
             };
-            for _ in 0..iterations {
-                deck.shuffle();
+            let deck_size = deck.cards().count();
+            let mut rng = rng.rng();
+            for iteration in 1..=iterations {
+                deck.shuffle_by(method, &mut rng);
+                if method != ShuffleMethod::Uniform {
+                    eprint!(
+                        "{:>3}: {:>2} rising sequences",
+                        iteration,
+                        deck.rising_sequences()
+                    );
+                    if method == ShuffleMethod::Riffle {
+                        eprint!(
+                            "; distance from uniform {:.3}",
+                            riffle_variation_distance(deck_size, iteration)
+                        );
+                    }
+                    eprintln!();
+                }
+            }
+            if method != ShuffleMethod::Uniform {
+                eprintln!(
+                    "a uniformly random deck has {} rising sequences on average",
+                    expected_rising_sequences(deck_size)
+                );
             }
             print_deck(&deck);
         }
-        Passphrase { phrase } => {
-            let deck = Deck::from_passphrase(&phrase);
+        Keygen {
+            dice,
+            sides,
+            rng,
+            rolls,
+        } => {
+            let deck = if dice {
+                deck_from_dice(sides, &rolls)?
+            } else {
+                Deck::random_with(&mut rng.rng())
+            };
+            print_deck(&deck);
+        }
+        Passphrase { phrase, keying } => {
+            let deck = Deck::from_passphrase_with(&phrase, keying.options());
             print_deck(&deck);
         }
+        Fingerprint { deck_opts } => {
+            let fingerprint = deck_opts.deck()?.fingerprint();
+            println!("{}", fingerprint);
+            println!("{}", fingerprint.words());
+            println!("{}", fingerprint.letters());
+            println!("{}", fingerprint.nato());
+        }
+        Diagnose {
+            current,
+            letters,
+            rounds,
+            deck_opts,
+        } => {
+            let replay = match deck_opts.passphrase {
+                Some(ref phrase) => Replay::from_passphrase(phrase, deck_opts.keying.options()),
+                None => Replay::from_deck(deck_opts.deck()?),
+            };
+            let diagnosis = match (current, letters) {
+                (Some(current), _) => {
+                    let current = deck_opts.parsing.deck(&current)?;
+                    replay.diagnose_deck(&current, rounds)
+                }
+                (None, Some(letters)) => replay.diagnose_letters(&letters),
+                (None, None) => unreachable!("clap requires one of them"),
+            };
+            println!("{}", diagnosis);
+        }
+        Trace {
+            count,
+            instructions,
+            deck_opts,
+        } => {
+            let initial = match deck_opts.passphrase {
+                Some(_) => Deck::new(),
+                None => deck_opts.deck()?,
+            };
+            let phrase = deck_opts.passphrase.as_deref();
+            let keying = deck_opts.keying.options();
+            if instructions {
+                let mut instructions = Instructions::new(initial.clone());
+                trace(phrase, keying, initial, count, &mut instructions);
+                print!("{}", instructions);
+            } else {
+                println!(
+                    "{:>4}  {:<24}  {}",
+                    "",
+                    "initial deck",
+                    format_deck(&initial)
+                );
+                let mut row = 0;
+                let mut print_step = |step: Step, deck: &Deck| {
+                    row += 1;
+                    println!(
+                        "{:>4}  {:<24}  {}",
+                        row,
+                        step.to_string(),
+                        format_deck(deck)
+                    );
+                };
+                trace(phrase, keying, initial, count, &mut print_step);
+            }
+        }
+        Checkpoints {
+            every,
+            length,
+            deck_opts,
+        } => {
+            let checkpoints = solitaire::Checkpoints::every(deck_opts.deck()?, every, length);
+            println!("# position, jokers skipped, deck");
+            print!("{}", checkpoints);
+        }
         Encrypt { crypt_opts } => {
-            let deck = crypt_opts.deck()?;
-            println!("{}", encrypt(deck, &crypt_opts.message));
+            let deck = crypt_opts.run(Operation::Encrypt)?;
+            if crypt_opts.print_deck {
+                print_deck(&deck);
+            }
         }
         Decrypt { crypt_opts } => {
-            let deck = crypt_opts.deck()?;
-            println!("{}", decrypt(deck, &crypt_opts.message));
+            let deck = crypt_opts.run(Operation::Decrypt)?;
+            if crypt_opts.print_deck {
+                print_deck(&deck);
+            }
         }
     }
     Ok(())
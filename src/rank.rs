@@ -0,0 +1,309 @@
+//! Compact encodings of decks by their rank among all permutations.
+//!
+//! A deck of 54 cards is one of 54! orderings, so it can be written down as
+//! a single integer of about 237 bits. The [Lehmer code] of the deck gives its
+//! rank: the sorted deck has rank 0, and the reversed deck has rank 54! - 1.
+//!
+//! Ranks are rendered with a prefix naming their [`RankEncoding`], so that
+//! they can be told apart from a list of cards when parsing a
+//! [`MaybeDeck`](crate::deck::MaybeDeck).
+//!
+//! [Lehmer code]: https://en.wikipedia.org/wiki/Lehmer_code
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+const BASE26: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const BASE32: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const DECIMAL: &[u8] = b"0123456789";
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum RankError {
+    #[error("unknown rank encoding {0:?}; expected one of `b26`, `b32`, or `dec`")]
+    UnknownEncoding(String),
+    #[error("{0:?} is not a digit of this rank encoding")]
+    InvalidDigit(char),
+    #[error("a rank needs at least one digit")]
+    Empty,
+}
+
+/// A text encoding of a [`Rank`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RankEncoding {
+    /// the letters `A..=Z`, in groups of five
+    Base26,
+    /// the RFC 4648 base32 alphabet, `A..=Z` then `2..=7`
+    Base32,
+    /// the decimal digits
+    Decimal,
+}
+
+impl RankEncoding {
+    /// the prefix which identifies this encoding, without its trailing `:`
+    pub fn prefix(self) -> &'static str {
+        match self {
+            RankEncoding::Base26 => "b26",
+            RankEncoding::Base32 => "b32",
+            RankEncoding::Decimal => "dec",
+        }
+    }
+
+    fn digits(self) -> &'static [u8] {
+        match self {
+            RankEncoding::Base26 => BASE26,
+            RankEncoding::Base32 => BASE32,
+            RankEncoding::Decimal => DECIMAL,
+        }
+    }
+
+    fn base(self) -> u32 {
+        self.digits().len() as u32
+    }
+
+    /// how many digits are grouped together, if any
+    fn group_size(self) -> Option<usize> {
+        match self {
+            RankEncoding::Base26 => Some(5),
+            _ => None,
+        }
+    }
+
+    fn value(self, digit: char) -> Result<u32, RankError> {
+        let upper = digit.to_ascii_uppercase();
+        self.digits()
+            .iter()
+            .position(|&d| d as char == upper)
+            .map(|value| value as u32)
+            .ok_or(RankError::InvalidDigit(digit))
+    }
+}
+
+impl FromStr for RankEncoding {
+    type Err = RankError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "b26" | "base26" => Ok(RankEncoding::Base26),
+            "b32" | "base32" => Ok(RankEncoding::Base32),
+            "dec" | "decimal" => Ok(RankEncoding::Decimal),
+            _ => Err(RankError::UnknownEncoding(s.to_string())),
+        }
+    }
+}
+
+/// The index of a deck among all orderings of its cards.
+///
+/// This is a small unsigned big integer, stored as little-endian 32-bit
+/// limbs with no trailing zero limbs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rank(Vec<u32>);
+
+impl Rank {
+    /// the rank of the sorted deck
+    pub fn zero() -> Rank {
+        Rank::default()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// `n!`: the number of orderings of `n` cards
+    pub fn factorial(n: usize) -> Rank {
+        let mut out = Rank::from(1);
+        for k in 2..=n as u32 {
+            out.mul_add(k, 0);
+        }
+        out
+    }
+
+    /// `self = self * factor + addend`
+    pub(crate) fn mul_add(&mut self, factor: u32, addend: u32) {
+        let mut carry = addend as u64;
+        for limb in self.0.iter_mut() {
+            let product = *limb as u64 * factor as u64 + carry;
+            *limb = product as u32;
+            carry = product >> 32;
+        }
+        if carry > 0 {
+            self.0.push(carry as u32);
+        }
+        self.normalize();
+    }
+
+    /// `self = self / divisor`, returning the remainder
+    pub(crate) fn div_rem(&mut self, divisor: u32) -> u32 {
+        let mut remainder = 0_u64;
+        for limb in self.0.iter_mut().rev() {
+            let dividend = (remainder << 32) | *limb as u64;
+            *limb = (dividend / divisor as u64) as u32;
+            remainder = dividend % divisor as u64;
+        }
+        self.normalize();
+        remainder as u32
+    }
+
+    fn normalize(&mut self) {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+    }
+
+    /// the digits of this rank in `encoding`, most significant first,
+    /// padded with leading zero digits to at least `width`
+    fn digits(&self, encoding: RankEncoding, width: usize) -> Vec<u8> {
+        let mut rank = self.clone();
+        let mut digits = Vec::with_capacity(width);
+        while !rank.is_zero() || digits.len() < width.max(1) {
+            digits.push(encoding.digits()[rank.div_rem(encoding.base()) as usize]);
+        }
+        digits.reverse();
+        digits
+    }
+
+    /// Encode this rank as text, with its prefix.
+    ///
+    /// Leading zero digits pad the rank to `width` digits, so that every
+    /// deck of a given size encodes to the same length.
+    pub fn encode(&self, encoding: RankEncoding, width: usize) -> String {
+        let digits = self.digits(encoding, width);
+        let mut out = String::with_capacity(2 * digits.len());
+        out.push_str(encoding.prefix());
+        out.push(':');
+        for (idx, &digit) in digits.iter().enumerate() {
+            if let Some(group) = encoding.group_size() {
+                if idx > 0 && idx % group == 0 {
+                    out.push(' ');
+                }
+            }
+            out.push(digit as char);
+        }
+        out
+    }
+
+    /// the number of digits needed to encode the rank of any deck of `deck_size` cards
+    pub fn width(encoding: RankEncoding, deck_size: usize) -> usize {
+        let mut max = Rank::factorial(deck_size);
+        // n! - 1, borrowing through any zero limbs
+        for limb in max.0.iter_mut() {
+            let (value, borrow) = limb.overflowing_sub(1);
+            *limb = value;
+            if !borrow {
+                break;
+            }
+        }
+        max.normalize();
+        max.digits(encoding, 1).len()
+    }
+
+    /// Decode the digits of a rank, ignoring whitespace and case.
+    pub fn decode(encoding: RankEncoding, digits: &str) -> Result<Rank, RankError> {
+        let mut rank = Rank::zero();
+        let mut any = false;
+        for digit in digits.chars().filter(|c| !c.is_whitespace()) {
+            rank.mul_add(encoding.base(), encoding.value(digit)?);
+            any = true;
+        }
+        if !any {
+            return Err(RankError::Empty);
+        }
+        Ok(rank)
+    }
+}
+
+impl From<u32> for Rank {
+    fn from(value: u32) -> Rank {
+        let mut out = Rank(vec![value]);
+        out.normalize();
+        out
+    }
+}
+
+impl PartialOrd for Rank {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rank {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .len()
+            .cmp(&other.0.len())
+            .then_with(|| self.0.iter().rev().cmp(other.0.iter().rev()))
+    }
+}
+
+impl fmt::Display for Rank {
+    /// Display the rank in decimal, without a prefix.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.digits(RankEncoding::Decimal, 1);
+        f.write_str(std::str::from_utf8(&digits).expect("decimal digits are ascii"))
+    }
+}
+
+impl FromStr for Rank {
+    type Err = RankError;
+
+    /// Parse a rank, either with an encoding prefix or as plain decimal.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().split_once(':') {
+            Some((prefix, digits)) => Rank::decode(prefix.trim().parse()?, digits),
+            None => Rank::decode(RankEncoding::Decimal, s),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arithmetic() {
+        let mut rank = Rank::factorial(20);
+        assert_eq!(rank.to_string(), "2432902008176640000");
+        assert_eq!(rank.div_rem(20), 0);
+        assert_eq!(rank, Rank::factorial(19));
+        assert!(Rank::factorial(19) < Rank::factorial(20));
+        assert_eq!(Rank::zero().to_string(), "0");
+    }
+
+    #[test]
+    fn test_width() {
+        assert_eq!(Rank::width(RankEncoding::Decimal, 54), 72);
+        assert_eq!(Rank::width(RankEncoding::Base26, 54), 51);
+        assert_eq!(Rank::width(RankEncoding::Base32, 54), 48);
+        // 4! - 1 == 23
+        assert_eq!(Rank::width(RankEncoding::Decimal, 4), 2);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let rank = Rank::factorial(54);
+        for encoding in &[
+            RankEncoding::Base26,
+            RankEncoding::Base32,
+            RankEncoding::Decimal,
+        ] {
+            let text = rank.encode(*encoding, 0);
+            assert_eq!(text.parse::<Rank>().unwrap(), rank);
+            assert_eq!(text.to_lowercase().parse::<Rank>().unwrap(), rank);
+        }
+        assert_eq!(
+            Rank::from(27).encode(RankEncoding::Base26, 7),
+            "b26:AAAAA BB"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            "hex:ff".parse::<Rank>(),
+            Err(RankError::UnknownEncoding("hex".into()))
+        );
+        assert_eq!("b32:AB1".parse::<Rank>(), Err(RankError::InvalidDigit('1')));
+        assert_eq!("dec: ".parse::<Rank>(), Err(RankError::Empty));
+    }
+}
@@ -0,0 +1,210 @@
+use crate::{crypt, CipherConfig, CipherError, Deck, Keystream, Operation};
+use rand::{CryptoRng, Rng};
+
+/// A Session encrypts or decrypts a sequence of messages under a single deck.
+///
+/// Each message picks up the keystream exactly where the previous one ended,
+/// so consecutive messages never share keystream. At any point, the current
+/// deck can be retrieved to continue the session later.
+pub struct Session {
+    keystream: Keystream,
+    config: CipherConfig,
+}
+
+impl From<Deck> for Session {
+    fn from(deck: Deck) -> Self {
+        Session::new(deck)
+    }
+}
+
+impl Session {
+    /// begin a new session from a pre-prepared deck
+    pub fn new(deck: Deck) -> Session {
+        Session::with_config(deck, CipherConfig::default())
+    }
+
+    /// begin a new session whose messages are read and written according to `config`
+    ///
+    /// The session may also continue an existing keystream, such as one
+    /// resumed from a [`Checkpoint`](crate::Checkpoint).
+    pub fn with_config(keystream: impl Into<Keystream>, config: CipherConfig) -> Session {
+        Session {
+            keystream: keystream.into(),
+            config,
+        }
+    }
+
+    /// encrypt the next message in this session
+    pub fn encrypt(&mut self, text: &str) -> String {
+        self.encrypt_with_rng(text, &mut rand::thread_rng())
+    }
+
+    /// encrypt the next message, drawing any indicator from the given source of randomness
+    pub fn encrypt_with_rng<R: Rng + CryptoRng>(&mut self, text: &str, rng: &mut R) -> String {
+        crypt(
+            &mut self.keystream,
+            &self.config,
+            text,
+            Operation::Encrypt,
+            rng,
+        )
+        .expect("encryption cannot fail")
+    }
+
+    /// decrypt the next message in this session
+    ///
+    /// Fails if the configuration's padding can be verified and is malformed,
+    /// or if the message's authentication tag does not match. On failure,
+    /// the session does not advance, so the next message still decrypts.
+    pub fn decrypt(&mut self, text: &str) -> Result<String, CipherError> {
+        let mut keystream = self.keystream.clone();
+        let plaintext = crypt(
+            &mut keystream,
+            &self.config,
+            text,
+            Operation::Decrypt,
+            &mut rand::thread_rng(),
+        )?;
+        self.keystream = keystream;
+        Ok(plaintext)
+    }
+
+    /// how many keystream values have been used, counting any used before
+    /// the session began
+    pub fn position(&self) -> usize {
+        self.keystream.position()
+    }
+
+    /// the current state of the deck
+    ///
+    /// A new session begun from this deck continues where this one stands.
+    pub fn deck(&self) -> &Deck {
+        self.keystream.deck()
+    }
+
+    /// end this session, returning the current state of the deck
+    pub fn into_deck(self) -> Deck {
+        self.keystream.into_deck()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decrypt, encrypt, Alphabet};
+
+    fn config(alphabet: Alphabet, preserve: bool) -> CipherConfig {
+        CipherConfig::builder()
+            .alphabet(alphabet)
+            .preserve_format(preserve)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_session_continues_keystream() {
+        let mut session = Session::new(Deck::new());
+        assert_eq!(session.encrypt("aaaaa"), "EXKYI");
+        assert_eq!(session.encrypt("aaaaa"), "ZSGEH");
+        assert_eq!(session.position(), 10);
+    }
+
+    #[test]
+    fn test_session_matches_single_message() {
+        let deck = Deck::from_passphrase("cryptonomicon");
+        let mut session = Session::new(deck.clone());
+        let parts = [session.encrypt("solit"), session.encrypt("airex")];
+        assert_eq!(parts.join(" "), encrypt(deck, "solitairex"));
+    }
+
+    #[test]
+    fn test_session_resume_from_deck() {
+        let deck = Deck::from_passphrase("foo");
+        let mut sender = Session::new(deck.clone());
+        let first = sender.encrypt("hello");
+        let resumed = sender.into_deck();
+        let second = Session::new(resumed.clone()).encrypt("world");
+
+        let mut receiver = Session::new(deck);
+        assert_eq!(receiver.decrypt(&first).unwrap(), "HELLO");
+        assert_eq!(receiver.deck(), &resumed);
+        assert_eq!(receiver.decrypt(&second).unwrap(), "WORLD");
+        assert_eq!(decrypt(resumed, &second), "WORLD");
+    }
+
+    #[test]
+    fn test_session_resume_from_checkpoint() {
+        let deck = Deck::from_passphrase("foo");
+        let mut sender = Session::new(deck.clone());
+        sender.encrypt("hello");
+        let second = sender.encrypt("world");
+
+        let checkpoints =
+            crate::Checkpoints::every(deck, std::num::NonZeroUsize::new(3).unwrap(), 9);
+        let keystream = checkpoints.keystream_at(5).unwrap();
+        let mut receiver = Session::with_config(keystream, CipherConfig::default());
+        assert_eq!(receiver.decrypt(&second).unwrap(), "WORLD");
+        assert_eq!(receiver.position(), 10);
+    }
+
+    #[test]
+    fn test_session_survives_bad_message() {
+        let config = CipherConfig::builder().tag_length(5).build().unwrap();
+        let deck = Deck::from_passphrase("foo");
+        let mut sender = Session::with_config(deck.clone(), config.clone());
+        let first = sender.encrypt("hello");
+        let second = sender.encrypt("world");
+
+        let mut receiver = Session::with_config(deck.clone(), config);
+        let mut altered: Vec<char> = first.chars().collect();
+        altered[0] = if altered[0] == 'A' { 'B' } else { 'A' };
+        let altered: String = altered.into_iter().collect();
+        assert_eq!(receiver.decrypt(&altered), Err(CipherError::TagMismatch));
+        assert_eq!(receiver.deck(), &deck);
+        assert_eq!(receiver.decrypt(&first).unwrap(), "HELLO");
+        assert_eq!(receiver.decrypt(&second).unwrap(), "WORLD");
+    }
+
+    #[test]
+    fn test_session_alphabets() {
+        for alphabet in &[
+            Alphabet::alphanumeric(),
+            Alphabet::greek(),
+            Alphabet::cyrillic(),
+        ] {
+            let msg: String = alphabet.symbols().iter().collect();
+            let deck = Deck::from_passphrase("alphabet");
+            let ciphertext =
+                Session::with_config(deck.clone(), config(alphabet.clone(), false)).encrypt(&msg);
+            assert!(ciphertext
+                .chars()
+                .all(|c| c == ' ' || alphabet.value(c).is_some()));
+            let plaintext = Session::with_config(deck, config(alphabet.clone(), false))
+                .decrypt(&ciphertext)
+                .unwrap();
+            assert!(plaintext.replace(' ', "").starts_with(&msg));
+        }
+    }
+
+    #[test]
+    fn test_session_preserving() {
+        let deck = Deck::from_passphrase("format");
+        let mut sender = Session::with_config(deck.clone(), config(Alphabet::cyrillic(), true));
+        let mut receiver = Session::with_config(deck, config(Alphabet::cyrillic(), true));
+        for msg in &["Привет, мир!", "Встреча в 10:00 (Ж)."] {
+            let ciphertext = sender.encrypt(msg);
+            assert_eq!(&receiver.decrypt(&ciphertext).unwrap(), msg);
+        }
+        assert_eq!(sender.deck(), receiver.deck());
+    }
+
+    #[test]
+    fn test_alphanumeric_keeps_digits() {
+        let deck = Deck::from_passphrase("digits");
+        let mut sender =
+            Session::with_config(deck.clone(), config(Alphabet::alphanumeric(), false));
+        let ciphertext = sender.encrypt("meet at 1600");
+        let mut receiver = Session::with_config(deck, config(Alphabet::alphanumeric(), false));
+        assert_eq!(receiver.decrypt(&ciphertext).unwrap(), "MEETA T1600");
+    }
+}
@@ -0,0 +1,799 @@
+//! Streaming encryption and decryption over [`std::io`].
+//!
+//! Letters are pushed through the keystream as soon as they arrive, and the
+//! output is emitted in groups without ever buffering the whole message.
+//! Input is decoded as UTF-8; only symbols of the configured alphabet are
+//! significant, and everything else, including invalid UTF-8, is dropped,
+//! unless the configuration preserves the format of the text.
+//!
+//! Authenticated messages are the exception: the plaintext values are kept,
+//! one byte per letter, so that the deck can absorb them once the body is
+//! complete. When decrypting, the final characters are also held back until
+//! it is known whether they belong to the tag.
+
+use crate::{CipherConfig, CipherError, Deck, Keystream, Operation, Padding};
+use rand::{CryptoRng, RngCore};
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+
+/// size of the chunks read from the inner reader of a [`SolitaireReader`]
+const CHUNK_SIZE: usize = 4096;
+
+/// The state shared by streaming readers and writers.
+struct StreamCipher {
+    keystream: Keystream,
+    operation: Operation,
+    config: CipherConfig,
+    /// how many values have been transformed so far
+    transformed: usize,
+    /// how many symbols have been emitted so far
+    emitted: usize,
+    /// decrypted values which are held back because they might be padding
+    held: VecDeque<u8>,
+    /// whether the indicator, if any, has been emitted or fully read
+    begun: bool,
+    /// the source of randomness for the indicator, if not the thread's
+    rng: Option<Box<dyn RngCore + Send>>,
+    /// the values of the indicator read so far
+    indicator: Vec<u8>,
+    /// whether the separator after the indicator has yet to be removed
+    skip_separator: bool,
+    /// the start of a UTF-8 sequence which was split between writes
+    pending: Vec<u8>,
+    /// the plaintext values, kept for authentication
+    plaintext: Vec<u8>,
+    /// input characters held back because they might belong to the tag, or
+    /// because they might be whitespace which follows it
+    tail: VecDeque<char>,
+    /// how many symbols are in `tail`
+    tail_symbols: usize,
+}
+
+impl StreamCipher {
+    fn new(keystream: Keystream, operation: Operation) -> StreamCipher {
+        StreamCipher {
+            keystream,
+            operation,
+            config: CipherConfig::default(),
+            transformed: 0,
+            emitted: 0,
+            held: VecDeque::new(),
+            begun: false,
+            rng: None,
+            indicator: Vec::new(),
+            skip_separator: false,
+            pending: Vec::new(),
+            plaintext: Vec::new(),
+            tail: VecDeque::new(),
+            tail_symbols: 0,
+        }
+    }
+
+    /// when encrypting, begin the message by keying the deck with a random
+    /// indicator and emitting it
+    fn begin(&mut self, out: &mut Vec<u8>) {
+        if self.begun || self.operation == Operation::Decrypt {
+            return;
+        }
+        self.begun = true;
+        if self.config.indicator_length == 0 {
+            return;
+        }
+        let alphabet = &self.config.alphabet;
+        let length = self.config.indicator_length;
+        let indicator = match self.rng {
+            Some(ref mut rng) => alphabet.random_values(length, rng),
+            None => alphabet.random_values(length, &mut rand::thread_rng()),
+        };
+        self.keystream
+            .deck_mut()
+            .key_values(indicator.iter().copied());
+        let mut text: String = indicator.into_iter().map(|v| alphabet.symbol(v)).collect();
+        text.push(self.config.separator);
+        out.extend_from_slice(text.as_bytes());
+    }
+
+    /// when decrypting, collect a value of the indicator
+    ///
+    /// Returns `false` if the indicator was already complete.
+    fn read_indicator(&mut self, value: u8) -> bool {
+        if self.begun || self.operation == Operation::Encrypt {
+            return false;
+        }
+        self.indicator.push(value);
+        if self.indicator.len() >= self.config.indicator_length {
+            self.begun = true;
+            self.skip_separator = self.config.preserve;
+            self.keystream
+                .deck_mut()
+                .key_values(self.indicator.iter().copied());
+        }
+        true
+    }
+
+    /// whether the input is still within the indicator of a message being decrypted
+    fn in_indicator(&self) -> bool {
+        self.operation == Operation::Decrypt && !self.begun && self.config.indicator_length > 0
+    }
+
+    /// whether decrypted values must be held back until the padding is known
+    fn holds_padding(&self) -> bool {
+        self.operation == Operation::Decrypt
+            && self.config.padding == Padding::LengthMarked
+            && !self.config.preserve
+    }
+
+    /// whether input must be held back until the tag is known
+    fn holds_tag(&self) -> bool {
+        self.operation == Operation::Decrypt && self.config.tag_length > 0
+    }
+
+    /// whether whitespace must be held back in case it ends the message, to
+    /// follow the tag
+    fn holds_trailing(&self) -> bool {
+        self.operation == Operation::Encrypt && self.config.preserve && self.config.tag_length > 0
+    }
+
+    /// copy any whitespace held back by `holds_trailing`, which turned out
+    /// not to end the message
+    fn release_trailing(&mut self, out: &mut Vec<u8>) {
+        while let Some(c) = self.tail.pop_front() {
+            self.push_char(c, out);
+        }
+    }
+
+    /// append the symbol for a transformed value, and any required separator, to `out`
+    ///
+    /// The symbol takes the case of `like`.
+    fn emit(&mut self, value: u8, like: char, out: &mut Vec<u8>) {
+        let mut symbol = String::with_capacity(8);
+        if let Some(separator) = self.config.separator_before(self.emitted) {
+            symbol.push(separator);
+        }
+        self.config
+            .alphabet
+            .push_symbol_like(&mut symbol, value, like);
+        out.extend_from_slice(symbol.as_bytes());
+        self.emitted += 1;
+    }
+
+    /// transform a single value, emitting it unless it might be padding
+    fn push_value(&mut self, value: u8, like: char, out: &mut Vec<u8>) {
+        let key = self
+            .keystream
+            .next()
+            .expect("keystream is an infinite iterator");
+        let output = self
+            .operation
+            .apply(value, key, self.config.alphabet.size());
+        if self.config.tag_length > 0 {
+            let plaintext = match self.operation {
+                Operation::Encrypt => value,
+                Operation::Decrypt => output,
+            };
+            self.plaintext.push(plaintext);
+        }
+        self.transformed += 1;
+        if self.holds_padding() {
+            self.held.push_back(output);
+            if self.held.len() > self.config.pad_length() {
+                let value = self.held.pop_front().expect("held values are not empty");
+                self.emit(value, like, out);
+            }
+        } else {
+            self.emit(output, like, out);
+        }
+    }
+
+    /// transform every symbol in `text`, discarding or copying everything else
+    fn push_str(&mut self, text: &str, out: &mut Vec<u8>) {
+        self.begin(out);
+        for c in text.chars() {
+            if self.holds_trailing() {
+                if self.config.is_trailing(c) {
+                    self.tail.push_back(c);
+                } else {
+                    self.release_trailing(out);
+                    self.push_char(c, out);
+                }
+                continue;
+            }
+            if !self.holds_tag() {
+                self.push_char(c, out);
+                continue;
+            }
+            self.tail.push_back(c);
+            if self.config.symbol_value(c).is_some() {
+                self.tail_symbols += 1;
+            }
+            while self.tail_symbols > self.config.tag_length {
+                let c = self.tail.pop_front().expect("tail contains symbols");
+                if self.config.symbol_value(c).is_some() {
+                    self.tail_symbols -= 1;
+                }
+                self.push_char(c, out);
+            }
+        }
+    }
+
+    /// transform a single char of input, discarding or copying it if it is not a symbol
+    fn push_char(&mut self, c: char, out: &mut Vec<u8>) {
+        if std::mem::take(&mut self.skip_separator) && c == self.config.separator {
+            return;
+        }
+        if self.in_indicator() {
+            if let Some(value) = self.config.symbol_value(c) {
+                self.read_indicator(value);
+            }
+            return;
+        }
+        match self.config.symbol_value(c) {
+            Some(value) if self.config.preserve => self.push_value(value, c, out),
+            Some(value) => self.push_value(value, 'A', out),
+            None if self.config.preserve => {
+                let mut buf = [0; 4];
+                out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+            None => {}
+        }
+    }
+
+    /// decode `bytes` as UTF-8 and transform every symbol
+    ///
+    /// An incomplete sequence at the end of `bytes` is kept until the next call.
+    fn push_bytes(&mut self, bytes: &[u8], out: &mut Vec<u8>) {
+        let mut input = std::mem::take(&mut self.pending);
+        input.extend_from_slice(bytes);
+        let mut rest = &input[..];
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(text) => {
+                    self.push_str(text, out);
+                    break;
+                }
+                Err(err) => {
+                    let (valid, invalid) = rest.split_at(err.valid_up_to());
+                    let valid = std::str::from_utf8(valid).expect("prefix was validated");
+                    self.push_str(valid, out);
+                    match err.error_len() {
+                        Some(len) => {
+                            if self.holds_tag() {
+                                // keep the invalid sequence in order with the held characters
+                                self.push_str("\u{FFFD}", out);
+                            } else if self.config.preserve && !self.in_indicator() {
+                                self.release_trailing(out);
+                                out.extend_from_slice(&invalid[..len]);
+                            }
+                            rest = &invalid[len..];
+                        }
+                        None => {
+                            self.pending = invalid.to_vec();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// complete the message by padding it, or by verifying and removing its
+    /// padding, then append or verify its tag
+    fn finish(&mut self, out: &mut Vec<u8>) -> Result<(), CipherError> {
+        self.begin(out);
+        let mut expected_tag = Vec::new();
+        let mut trailing = Vec::new();
+        if self.holds_tag() && self.config.preserve && self.pending.is_empty() {
+            while let Some(c) = self.tail.back().copied() {
+                if !self.config.is_trailing(c) {
+                    break;
+                }
+                self.tail.pop_back();
+                trailing.push(c);
+            }
+            trailing.reverse();
+        }
+        if self.holds_trailing() && self.pending.is_empty() {
+            trailing.extend(self.tail.drain(..));
+        }
+        if self.holds_tag() {
+            if self.tail_symbols < self.config.tag_length {
+                return Err(CipherError::MissingTag);
+            }
+            let mut lead = String::new();
+            while let Some(c) = self.tail.pop_front() {
+                match self.config.symbol_value(c) {
+                    Some(value) => expected_tag.push(value),
+                    None if expected_tag.is_empty() => lead.push(c),
+                    None => {}
+                }
+            }
+            if self.config.preserve && lead.ends_with(self.config.separator) {
+                lead.pop();
+            }
+            for c in lead.chars() {
+                self.push_char(c, out);
+            }
+        }
+
+        self.finish_body(out)?;
+
+        if self.config.tag_length > 0 {
+            let plaintext = std::mem::take(&mut self.plaintext);
+            let tag = crate::authenticate(&mut self.keystream, &self.config, plaintext);
+            match self.operation {
+                Operation::Encrypt => {
+                    let mut text = String::with_capacity(tag.len() + 1);
+                    text.push(self.config.separator);
+                    text.extend(tag.into_iter().map(|v| self.config.alphabet.symbol(v)));
+                    out.extend_from_slice(text.as_bytes());
+                }
+                Operation::Decrypt if tag != expected_tag => return Err(CipherError::TagMismatch),
+                Operation::Decrypt => {}
+            }
+        }
+        let trailing: String = trailing.into_iter().collect();
+        out.extend_from_slice(trailing.as_bytes());
+        Ok(())
+    }
+
+    /// complete the body of the message by padding it, or by verifying and
+    /// removing its padding
+    ///
+    /// When preserving the format, nothing is padded, but any incomplete
+    /// UTF-8 sequence is copied through.
+    fn finish_body(&mut self, out: &mut Vec<u8>) -> Result<(), CipherError> {
+        if self.in_indicator() {
+            return Err(CipherError::MissingIndicator);
+        }
+        if self.config.preserve {
+            self.release_trailing(out);
+            out.append(&mut self.pending);
+            return Ok(());
+        }
+        if self.holds_padding() {
+            let mut held: Vec<u8> = self.held.drain(..).collect();
+            if !self.transformed.is_multiple_of(self.config.pad_length()) {
+                return Err(CipherError::MalformedPadding);
+            }
+            self.config.unpad(&mut held)?;
+            for value in held {
+                self.emit(value, 'A', out);
+            }
+            return Ok(());
+        }
+        for value in self.config.padding_for(self.transformed) {
+            self.push_value(value, 'A', out);
+        }
+        Ok(())
+    }
+}
+
+/// A writer which encrypts or decrypts everything written to it.
+///
+/// Output is written to the inner writer in groups, as configured, as soon as
+/// it is available. Padding is only applied by
+/// [`finish`](SolitaireWriter::finish); dropping the writer without calling
+/// it leaves the final group unpadded. When decrypting with length-marked
+/// padding, the final group is held back until `finish` can remove it.
+pub struct SolitaireWriter<W: Write> {
+    inner: W,
+    cipher: StreamCipher,
+}
+
+impl<W: Write> SolitaireWriter<W> {
+    /// Create a new writer from a deck or an existing keystream.
+    pub fn new(inner: W, keystream: impl Into<Keystream>, operation: Operation) -> Self {
+        SolitaireWriter {
+            inner,
+            cipher: StreamCipher::new(keystream.into(), operation),
+        }
+    }
+
+    /// Read and write text according to `config` instead of the defaults.
+    pub fn with_config(mut self, config: CipherConfig) -> Self {
+        self.cipher.config = config;
+        self
+    }
+
+    /// Draw any indicator from `rng` instead of the thread's source of randomness.
+    pub fn with_rng<G: RngCore + CryptoRng + Send + 'static>(mut self, rng: G) -> Self {
+        self.cipher.rng = Some(Box::new(rng));
+        self
+    }
+
+    /// the current state of the deck
+    pub fn deck(&self) -> &Deck {
+        self.cipher.keystream.deck()
+    }
+
+    /// how many keystream values have been used so far
+    pub fn position(&self) -> usize {
+        self.cipher.keystream.position()
+    }
+
+    /// Pad the final group, or remove its padding, then flush.
+    ///
+    /// Returns the inner writer and the final state of the deck. Malformed
+    /// padding is reported as an error of kind [`io::ErrorKind::InvalidData`].
+    pub fn finish(mut self) -> io::Result<(W, Deck)> {
+        let mut out = Vec::new();
+        self.cipher
+            .finish(&mut out)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        self.inner.write_all(&out)?;
+        self.inner.flush()?;
+        Ok((self.inner, self.cipher.keystream.into_deck()))
+    }
+}
+
+impl<W: Write> Write for SolitaireWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut out = Vec::with_capacity(2 * buf.len());
+        self.cipher.push_bytes(buf, &mut out);
+        self.inner.write_all(&out)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A reader which encrypts or decrypts everything read through it.
+///
+/// Output is produced in groups, as configured. When the inner reader is
+/// exhausted, the final group is padded, or its padding is removed.
+/// Malformed padding is reported as an error of kind
+/// [`io::ErrorKind::InvalidData`].
+pub struct SolitaireReader<R: Read> {
+    inner: R,
+    cipher: StreamCipher,
+    buffer: Vec<u8>,
+    /// how much of `buffer` has already been read
+    consumed: usize,
+    finished: bool,
+}
+
+impl<R: Read> SolitaireReader<R> {
+    /// Create a new reader from a deck or an existing keystream.
+    pub fn new(inner: R, keystream: impl Into<Keystream>, operation: Operation) -> Self {
+        SolitaireReader {
+            inner,
+            cipher: StreamCipher::new(keystream.into(), operation),
+            buffer: Vec::new(),
+            consumed: 0,
+            finished: false,
+        }
+    }
+
+    /// Read and write text according to `config` instead of the defaults.
+    pub fn with_config(mut self, config: CipherConfig) -> Self {
+        self.cipher.config = config;
+        self
+    }
+
+    /// Draw any indicator from `rng` instead of the thread's source of randomness.
+    pub fn with_rng<G: RngCore + CryptoRng + Send + 'static>(mut self, rng: G) -> Self {
+        self.cipher.rng = Some(Box::new(rng));
+        self
+    }
+
+    /// the current state of the deck
+    pub fn deck(&self) -> &Deck {
+        self.cipher.keystream.deck()
+    }
+
+    /// how many keystream values have been used so far
+    pub fn position(&self) -> usize {
+        self.cipher.keystream.position()
+    }
+
+    /// Stop reading, returning the inner reader and the current state of the deck.
+    pub fn into_inner(self) -> (R, Deck) {
+        (self.inner, self.cipher.keystream.into_deck())
+    }
+
+    /// refill the buffer from the inner reader
+    fn fill(&mut self) -> io::Result<()> {
+        let mut chunk = [0; CHUNK_SIZE];
+        self.buffer.clear();
+        self.consumed = 0;
+        while self.buffer.is_empty() && !self.finished {
+            let n = match self.inner.read(&mut chunk) {
+                Ok(n) => n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            if n == 0 {
+                self.finished = true;
+                self.cipher
+                    .finish(&mut self.buffer)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            } else {
+                self.cipher.push_bytes(&chunk[..n], &mut self.buffer);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for SolitaireReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.consumed == self.buffer.len() {
+            self.fill()?;
+        }
+        let available = &self.buffer[self.consumed..];
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consumed += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decrypt, encrypt, Alphabet, Session};
+
+    const MESSAGE: &str =
+        "Two tires fly. Two wail. A bamboo grove, all chopped down. From it, warring songs.";
+
+    #[test]
+    fn test_writer_matches_encrypt() {
+        let deck = Deck::from_passphrase("cryptonomicon");
+        let mut writer = SolitaireWriter::new(Vec::new(), deck.clone(), Operation::Encrypt);
+        // write in awkwardly-sized pieces to exercise grouping across calls
+        for piece in MESSAGE.as_bytes().chunks(3) {
+            writer.write_all(piece).unwrap();
+        }
+        let (out, _) = writer.finish().unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), encrypt(deck, MESSAGE));
+    }
+
+    #[test]
+    fn test_writer_pads_only_on_finish() {
+        let mut writer = SolitaireWriter::new(Vec::new(), Deck::new(), Operation::Encrypt);
+        writer.write_all(b"aaaaaa").unwrap();
+        assert_eq!(writer.inner, b"EXKYI Z");
+        let (out, _) = writer.finish().unwrap();
+        assert_eq!(out.len(), 11);
+    }
+
+    #[test]
+    fn test_reader_matches_decrypt() {
+        let deck = Deck::from_passphrase("foo");
+        let ciphertext = encrypt(deck.clone(), MESSAGE);
+        let mut reader =
+            SolitaireReader::new(ciphertext.as_bytes(), deck.clone(), Operation::Decrypt);
+        let mut plaintext = String::new();
+        reader.read_to_string(&mut plaintext).unwrap();
+        assert_eq!(plaintext, decrypt(deck, &ciphertext));
+    }
+
+    #[test]
+    fn test_split_utf8() {
+        let msg = "СЪЕШЬ ЖЕ ЕЩЁ ЭТИХ МЯГКИХ ФРАНЦУЗСКИХ БУЛОК";
+        let deck = Deck::from_passphrase("cyrillic");
+        let config = CipherConfig::builder()
+            .alphabet(Alphabet::cyrillic())
+            .groups_per_line(3)
+            .build()
+            .unwrap();
+        let expect = Session::with_config(deck.clone(), config.clone()).encrypt(msg);
+        let mut writer =
+            SolitaireWriter::new(Vec::new(), deck, Operation::Encrypt).with_config(config);
+        // every cyrillic letter is two bytes long, so this splits each of them
+        for piece in msg.as_bytes().chunks(3) {
+            writer.write_all(piece).unwrap();
+        }
+        let (out, _) = writer.finish().unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), expect);
+    }
+
+    #[test]
+    fn test_preserving_format() {
+        let deck = Deck::from_passphrase("cryptonomicon");
+        let config = CipherConfig::builder()
+            .preserve_format(true)
+            .build()
+            .unwrap();
+        let mut writer =
+            SolitaireWriter::new(Vec::new(), deck.clone(), Operation::Encrypt).with_config(config);
+        for piece in MESSAGE.as_bytes().chunks(7) {
+            writer.write_all(piece).unwrap();
+        }
+        let (out, _) = writer.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            crate::encrypt_preserving(deck, MESSAGE)
+        );
+    }
+
+    #[test]
+    fn test_preserving_irregular_case() {
+        let text = "Straſse ıs KELVIN \u{212A}";
+        let deck = Deck::from_passphrase("key");
+        for &tag_length in &[0, 5] {
+            let config = CipherConfig::builder()
+                .preserve_format(true)
+                .tag_length(tag_length)
+                .build()
+                .unwrap();
+            let expect = crate::encrypt_with(deck.clone(), text, &config);
+            let mut writer = SolitaireWriter::new(Vec::new(), deck.clone(), Operation::Encrypt)
+                .with_config(config.clone());
+            for c in text.chars() {
+                write!(writer, "{}", c).unwrap();
+            }
+            let (out, _) = writer.finish().unwrap();
+            let ciphertext = String::from_utf8(out).unwrap();
+            assert_eq!(ciphertext, expect);
+
+            let mut reader =
+                SolitaireReader::new(ciphertext.as_bytes(), deck.clone(), Operation::Decrypt)
+                    .with_config(config);
+            let mut plaintext = String::new();
+            reader.read_to_string(&mut plaintext).unwrap();
+            assert_eq!(plaintext, text);
+        }
+    }
+
+    #[test]
+    fn test_tag_precedes_trailing_newline() {
+        let text = "hello world\n";
+        let deck = Deck::from_passphrase("key");
+        let config = CipherConfig::builder()
+            .preserve_format(true)
+            .tag_length(5)
+            .build()
+            .unwrap();
+        let expect = crate::encrypt_with(deck.clone(), text, &config);
+        assert_eq!(
+            expect,
+            crate::encrypt_with(deck.clone(), "hello world", &config) + "\n"
+        );
+        let mut writer = SolitaireWriter::new(Vec::new(), deck.clone(), Operation::Encrypt)
+            .with_config(config.clone());
+        writer.write_all(text.as_bytes()).unwrap();
+        let (out, _) = writer.finish().unwrap();
+        let ciphertext = String::from_utf8(out).unwrap();
+        assert_eq!(ciphertext, expect);
+
+        let mut reader =
+            SolitaireReader::new(ciphertext.as_bytes(), deck.clone(), Operation::Decrypt)
+                .with_config(config.clone());
+        let mut plaintext = String::new();
+        reader.read_to_string(&mut plaintext).unwrap();
+        assert_eq!(plaintext, text);
+        assert_eq!(
+            crate::decrypt_with(deck, &ciphertext, &config).unwrap(),
+            text
+        );
+    }
+
+    #[test]
+    fn test_length_marked_padding() {
+        let config = CipherConfig::builder()
+            .padding(Padding::LengthMarked)
+            .build()
+            .unwrap();
+        let deck = Deck::from_passphrase("padding");
+        let ciphertext = crate::encrypt_with(deck.clone(), MESSAGE, &config);
+
+        let mut writer = SolitaireWriter::new(Vec::new(), deck.clone(), Operation::Decrypt)
+            .with_config(config.clone());
+        for piece in ciphertext.as_bytes().chunks(4) {
+            writer.write_all(piece).unwrap();
+        }
+        let (out, _) = writer.finish().unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            crate::decrypt_with(deck.clone(), &ciphertext, &config).unwrap()
+        );
+
+        let truncated = &ciphertext[..ciphertext.len() - 1];
+        let mut reader = SolitaireReader::new(truncated.as_bytes(), deck, Operation::Decrypt)
+            .with_config(config);
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_indicator() {
+        for preserve in &[false, true] {
+            let config = CipherConfig::builder()
+                .indicator_length(5)
+                .preserve_format(*preserve)
+                .build()
+                .unwrap();
+            let deck = Deck::from_passphrase("indicator");
+            let mut writer = SolitaireWriter::new(Vec::new(), deck.clone(), Operation::Encrypt)
+                .with_config(config.clone());
+            for piece in MESSAGE.as_bytes().chunks(5) {
+                writer.write_all(piece).unwrap();
+            }
+            let (ciphertext, _) = writer.finish().unwrap();
+            let ciphertext = String::from_utf8(ciphertext).unwrap();
+
+            let expect = crate::decrypt_with(deck.clone(), &ciphertext, &config).unwrap();
+            let mut writer =
+                SolitaireWriter::new(Vec::new(), deck, Operation::Decrypt).with_config(config);
+            for piece in ciphertext.as_bytes().chunks(2) {
+                writer.write_all(piece).unwrap();
+            }
+            let (plaintext, _) = writer.finish().unwrap();
+            assert_eq!(String::from_utf8(plaintext).unwrap(), expect);
+            if *preserve {
+                assert_eq!(expect, MESSAGE);
+            }
+        }
+    }
+
+    #[test]
+    fn test_indicator_rng() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let config = CipherConfig::builder().indicator_length(5).build().unwrap();
+        let deck = Deck::from_passphrase("indicator");
+        let mut writer = SolitaireWriter::new(Vec::new(), deck.clone(), Operation::Encrypt)
+            .with_config(config.clone())
+            .with_rng(StdRng::seed_from_u64(1));
+        writer.write_all(MESSAGE.as_bytes()).unwrap();
+        let (ciphertext, _) = writer.finish().unwrap();
+        let expect = crate::encrypt_with_rng(deck, MESSAGE, &config, &mut StdRng::seed_from_u64(1));
+        assert_eq!(String::from_utf8(ciphertext).unwrap(), expect);
+    }
+
+    #[test]
+    fn test_authentication() {
+        for preserve in &[false, true] {
+            let config = CipherConfig::builder()
+                .tag_length(4)
+                .indicator_length(3)
+                .preserve_format(*preserve)
+                .build()
+                .unwrap();
+            let deck = Deck::from_passphrase("authentic");
+            let mut writer = SolitaireWriter::new(Vec::new(), deck.clone(), Operation::Encrypt)
+                .with_config(config.clone());
+            for piece in MESSAGE.as_bytes().chunks(5) {
+                writer.write_all(piece).unwrap();
+            }
+            let (ciphertext, _) = writer.finish().unwrap();
+            let ciphertext = String::from_utf8(ciphertext).unwrap();
+
+            let expect = crate::decrypt_with(deck.clone(), &ciphertext, &config).unwrap();
+            let mut reader =
+                SolitaireReader::new(ciphertext.as_bytes(), deck.clone(), Operation::Decrypt)
+                    .with_config(config.clone());
+            let mut plaintext = String::new();
+            reader.read_to_string(&mut plaintext).unwrap();
+            assert_eq!(plaintext, expect);
+
+            let altered = ciphertext.replacen(|c: char| c.is_alphabetic(), "", 1) + "A";
+            let mut writer =
+                SolitaireWriter::new(Vec::new(), deck, Operation::Decrypt).with_config(config);
+            writer.write_all(altered.as_bytes()).unwrap();
+            let err = writer.finish().unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_final_deck_continues_keystream() {
+        let mut writer = SolitaireWriter::new(Vec::new(), Deck::new(), Operation::Encrypt);
+        writer.write_all(b"aaaaa").unwrap();
+        let (_, deck) = writer.finish().unwrap();
+        assert_eq!(encrypt(deck, "aaaaa"), "ZSGEH");
+    }
+
+    #[test]
+    fn test_streaming_keeps_no_history() {
+        let mut writer = SolitaireWriter::new(io::sink(), Deck::new(), Operation::Encrypt);
+        for _ in 0..100 {
+            writer.write_all(&[b'a'; 100]).unwrap();
+        }
+        assert_eq!(writer.position(), 10_000);
+        assert!(writer.cipher.keystream.history.is_none());
+    }
+}
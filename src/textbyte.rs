@@ -1,3 +1,4 @@
+use crate::alphabet::Alphabet;
 use itertools::Itertools;
 use std::iter::FromIterator;
 
@@ -6,9 +7,20 @@ use std::iter::FromIterator;
 /// ASCII letters are uppercased, then assigned `A==1 .. Z==26`. All other chars
 /// are discarded.
 pub fn textbyte(text: &str) -> impl '_ + Iterator<Item = u8> {
-    text.chars()
-        .filter(char::is_ascii_alphabetic)
-        .map(|c| (c.to_ascii_uppercase() as u8) - b'A' + 1)
+    text.chars().filter_map(letter_value)
+}
+
+/// Convert a single char into its value in `1..=26`, if it is an ASCII letter.
+pub(crate) fn letter_value(c: char) -> Option<u8> {
+    c.is_ascii_alphabetic()
+        .then(|| (c.to_ascii_uppercase() as u8) - b'A' + 1)
+}
+
+/// Convert a value into an uppercase letter, assuming `1==A .. 26==Z`.
+///
+/// Values above 26 wrap around.
+pub(crate) fn value_letter(b: u8) -> char {
+    (((b - 1) % 26) + b'A') as char
 }
 
 pub type Padded<'a, T> = Box<dyn 'a + Iterator<Item = T>>;
@@ -61,6 +73,13 @@ pub trait Restore<'a> {
     ///
     /// This is a fused iterator.
     fn restore(self) -> Restored<'a>;
+
+    /// Restore a stream of bytes into a stream of characters of `alphabet`.
+    ///
+    /// Values above the size of the alphabet wrap around.
+    ///
+    /// This is a fused iterator.
+    fn restore_with(self, alphabet: &'a Alphabet) -> Restored<'a>;
 }
 
 impl<'a, I> Restore<'a> for I
@@ -69,11 +88,11 @@ where
     <I as IntoIterator>::IntoIter: 'a,
 {
     fn restore(self) -> Restored<'a> {
-        Box::new(
-            self.into_iter()
-                .fuse()
-                .map(|b| (((b - 1) % 26) + b'A') as char),
-        )
+        Box::new(self.into_iter().fuse().map(value_letter))
+    }
+
+    fn restore_with(self, alphabet: &'a Alphabet) -> Restored<'a> {
+        Box::new(self.into_iter().fuse().map(move |b| alphabet.symbol(b)))
     }
 }
 
@@ -0,0 +1,153 @@
+//! Observing each step of the algorithm, for checking a hand computation.
+//!
+//! An [`Observer`] is shown the deck after every step taken while keying a
+//! deck with [`Deck::from_passphrase_observed`] or while generating keystream
+//! with [`Keystream::next_observed`](crate::Keystream::next_observed).
+
+use crate::card::Joker;
+use crate::deck::{Deck, DEFAULT_DECK_SIZE};
+use std::fmt;
+
+/// A single step of the algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// a joker was moved down the deck by some number of cards
+    Push { joker: Joker, by: usize },
+    /// the cards above the first joker were swapped with those below the second
+    TripleCut,
+    /// the deck was cut at the value of its bottom card
+    CountCut,
+    /// while keying, the deck was cut at the value of a letter of the passphrase
+    LetterCut { letter: u8 },
+    /// while keying, a joker was moved to lie below the `n`th other card
+    PlaceJoker { joker: Joker, n: u8 },
+    /// the keystream produced a value
+    Output { value: u8 },
+    /// the output card was a joker, so the steps are repeated
+    SkipJoker { joker: Joker },
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Push { joker, by } => write!(f, "push joker {:?} down {}", joker, by),
+            Step::TripleCut => write!(f, "triple cut"),
+            Step::CountCut => write!(f, "count cut"),
+            Step::LetterCut {
+                letter: letter @ 1..=26,
+            } => {
+                write!(
+                    f,
+                    "count cut at {} ({})",
+                    letter,
+                    (b'A' + letter - 1) as char
+                )
+            }
+            Step::LetterCut { letter } => write!(f, "count cut at {}", letter),
+            Step::PlaceJoker { joker, n } => write!(f, "place joker {:?} below {}", joker, n),
+            Step::Output { value } => write!(f, "output {}", value),
+            Step::SkipJoker { joker } => write!(f, "skip joker {:?}", joker),
+        }
+    }
+}
+
+/// Something which is shown the deck after every step of the algorithm.
+///
+/// Closures taking a [`Step`] and a deck are observers, as is `()`, which
+/// ignores every step.
+pub trait Observer<const DECK_SIZE: usize> {
+    fn observe(&mut self, step: Step, deck: &Deck<DECK_SIZE>);
+}
+
+impl<const DECK_SIZE: usize> Observer<DECK_SIZE> for () {
+    fn observe(&mut self, _step: Step, _deck: &Deck<DECK_SIZE>) {}
+}
+
+impl<F, const DECK_SIZE: usize> Observer<DECK_SIZE> for F
+where
+    F: FnMut(Step, &Deck<DECK_SIZE>),
+{
+    fn observe(&mut self, step: Step, deck: &Deck<DECK_SIZE>) {
+        self(step, deck)
+    }
+}
+
+/// An observer which records every step, with the deck after it.
+#[derive(Debug, Clone, Default)]
+pub struct Trace<const DECK_SIZE: usize = DEFAULT_DECK_SIZE> {
+    events: Vec<(Step, Deck<DECK_SIZE>)>,
+}
+
+impl<const DECK_SIZE: usize> Trace<DECK_SIZE> {
+    pub fn new() -> Trace<DECK_SIZE> {
+        Trace { events: Vec::new() }
+    }
+
+    /// every step observed, in order, with the deck after it
+    pub fn events(&self) -> &[(Step, Deck<DECK_SIZE>)] {
+        &self.events
+    }
+}
+
+impl<const DECK_SIZE: usize> Observer<DECK_SIZE> for Trace<DECK_SIZE> {
+    fn observe(&mut self, step: Step, deck: &Deck<DECK_SIZE>) {
+        self.events.push((step, deck.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Keystream;
+
+    #[test]
+    fn test_keystream_steps() {
+        // [1 2 3 4 5 6 7 8] -> [1 2 3 4 5 6 8 7] -> [1 8 2 3 4 5 6 7]
+        // -> [8 2 3 4 5 6 7 1] -> [2 3 4 5 6 7 8 1]; top card 2 selects 4
+        let mut trace = Trace::<8>::new();
+        let mut keystream = Keystream::from(Deck::<8>::new());
+        assert_eq!(keystream.next_observed(&mut trace), 4);
+        let steps: Vec<_> = trace
+            .events()
+            .iter()
+            .map(|(step, deck)| (step.to_string(), format!("{:?}", deck)))
+            .collect();
+        let expected = [
+            ("push joker A down 1", "[1 2 3 4 5 6 8 7]"),
+            ("push joker B down 2", "[1 8 2 3 4 5 6 7]"),
+            ("triple cut", "[8 2 3 4 5 6 7 1]"),
+            ("count cut", "[2 3 4 5 6 7 8 1]"),
+            ("output 4", "[2 3 4 5 6 7 8 1]"),
+        ];
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|(step, deck)| (step.to_string(), deck.to_string()))
+            .collect();
+        assert_eq!(steps, expected);
+    }
+
+    #[test]
+    fn test_matches_unobserved() {
+        let mut steps = Vec::new();
+        let deck: Deck =
+            Deck::from_passphrase_observed("trace", Default::default(), &mut |step, _: &Deck| {
+                steps.push(step)
+            });
+        assert_eq!(deck, Deck::from_passphrase("trace"));
+        assert_eq!(steps.len(), 5 * 5);
+        assert_eq!(steps[4], Step::LetterCut { letter: 20 });
+
+        let mut observed = Keystream::from(deck.clone());
+        let mut skipped = 0;
+        let mut count_skips = |step, _: &Deck| {
+            if let Step::SkipJoker { .. } = step {
+                skipped += 1;
+            }
+        };
+        let values: Vec<_> = (0..100)
+            .map(|_| observed.next_observed(&mut count_skips))
+            .collect();
+        assert!(Keystream::from(deck).take(100).eq(values));
+        assert!(skipped > 0);
+    }
+}